@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where yt-dlp's `--download-archive` file lives, if archiving is enabled.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveMode {
+    #[default]
+    Off,
+    /// One `.hmd-archive.txt` inside each output folder.
+    PerFolder,
+    /// A single archive shared by every output folder.
+    Global,
+}
+
+const FOLDER_ARCHIVE_NAME: &str = ".hmd-archive.txt";
+
+pub fn archive_file(mode: ArchiveMode, state_dir: &Path, output_dir: &str) -> Option<PathBuf> {
+    match mode {
+        ArchiveMode::Off => None,
+        ArchiveMode::PerFolder => Some(Path::new(output_dir).join(FOLDER_ARCHIVE_NAME)),
+        ArchiveMode::Global => Some(state_dir.join("archive.txt")),
+    }
+}
+
+/// Checks for the `<extractor> <id>` line yt-dlp writes after a successful download.
+pub fn contains(file: &Path, extractor: &str, video_id: &str) -> bool {
+    let Ok(text) = fs::read_to_string(file) else {
+        return false;
+    };
+    let extractor = extractor.to_lowercase();
+    text.lines().any(|line| {
+        let mut parts = line.split_whitespace();
+        parts.next().map(|e| e.to_lowercase()) == Some(extractor.clone()) && parts.next() == Some(video_id)
+    })
+}
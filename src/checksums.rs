@@ -0,0 +1,99 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Why a dependency could not be installed; `code` tells verification failures apart
+/// from ordinary download errors.
+#[derive(Serialize, Debug)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum DependencyError {
+    /// The download did not match the publisher's checksum and was discarded.
+    ChecksumMismatch { file: String, expected: String, actual: String, message: String },
+    /// The publisher's checksum list could not be fetched or does not list the file.
+    ChecksumUnavailable { file: String, message: String },
+    /// The verified download could not be moved into place, e.g. because the old
+    /// binary is still running; it was discarded and the old version kept.
+    InstallFailed { file: String, message: String },
+    Failed { message: String },
+}
+
+impl From<String> for DependencyError {
+    fn from(message: String) -> Self {
+        DependencyError::Failed { message }
+    }
+}
+
+impl std::fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyError::ChecksumMismatch { message, .. }
+            | DependencyError::ChecksumUnavailable { message, .. }
+            | DependencyError::InstallFailed { message, .. }
+            | DependencyError::Failed { message } => f.write_str(message),
+        }
+    }
+}
+
+fn is_sha256(token: &str) -> bool {
+    token.len() == 64 && token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Finds the hash for `file_name` in a checksum list: `sha256sum` output (`<hash>  <name>`,
+/// optionally `*<name>`), or a single-file listing such as PowerShell's `Get-FileHash`
+/// output that only contains one hash.
+pub fn parse(text: &str, file_name: &str) -> Option<String> {
+    let listed = text.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next().filter(|h| is_sha256(h))?;
+        let name = parts.next()?.trim_start_matches('*');
+        let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        (name == file_name).then(|| hash.to_lowercase())
+    });
+    if listed.is_some() {
+        return listed;
+    }
+    let mut hashes = text.split(|c: char| !c.is_ascii_hexdigit()).filter(|t| is_sha256(t));
+    match (hashes.next(), hashes.next()) {
+        (Some(only), None) => Some(only.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Downloads the checksum list at `sums_url` and returns the hash published for `file_name`.
+pub async fn published_hash(sums_url: &str, file_name: &str) -> Result<String, DependencyError> {
+    let unavailable = |message: String| DependencyError::ChecksumUnavailable { file: file_name.to_string(), message };
+    let response = reqwest::Client::builder()
+        .user_agent(crate::APP_USER_AGENT)
+        .build()
+        .map_err(|e| unavailable(e.to_string()))?
+        .get(sums_url)
+        .send()
+        .await
+        .map_err(|e| unavailable(format!("Failed to fetch checksums for {}: {}", file_name, e)))?;
+    if !response.status().is_success() {
+        return Err(unavailable(format!("Failed to fetch checksums for {}: {}", file_name, response.status())));
+    }
+    let text = response.text().await.map_err(|e| unavailable(e.to_string()))?;
+    parse(&text, file_name).ok_or_else(|| unavailable(format!("No published checksum for {}", file_name)))
+}
+
+/// Checks a downloaded file against `expected`, deleting it on a mismatch.
+pub fn verify(path: &Path, file_name: &str, expected: &str) -> Result<(), DependencyError> {
+    let actual = crate::compute_sha256(&path.to_path_buf())?;
+    if actual.eq_ignore_ascii_case(expected) {
+        return Ok(());
+    }
+    let _ = std::fs::remove_file(path);
+    Err(DependencyError::ChecksumMismatch {
+        file: file_name.to_string(),
+        expected: expected.to_string(),
+        actual: actual.clone(),
+        message: format!("{} failed verification (expected SHA-256 {}, got {})", file_name, expected, actual),
+    })
+}
+
+/// Temporary name next to `path` for a download that is not verified yet.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".download");
+    path.with_file_name(name)
+}
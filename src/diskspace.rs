@@ -0,0 +1,167 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::formats::FormatSelection;
+use crate::recode;
+
+/// Result of comparing a job's estimated size with the free space on its volume.
+#[derive(Serialize, Clone, Debug)]
+pub struct SpaceCheck {
+    /// Expected size of the finished file(s), in bytes.
+    pub estimated_bytes: u64,
+    /// Free space on the volume holding `path`, in bytes.
+    pub available_bytes: u64,
+    /// Folder whose volume was checked (the nearest existing parent of the output folder).
+    pub path: String,
+    /// The estimate uses `filesize_approx` or bitrates for at least one format.
+    pub approximate: bool,
+}
+
+impl SpaceCheck {
+    /// Not even the downloaded streams fit.
+    pub fn insufficient(&self) -> bool {
+        self.available_bytes < self.estimated_bytes
+    }
+
+    /// The streams fit, but merging or converting keeps a second copy around until
+    /// ffmpeg finishes, which may not.
+    pub fn tight(&self) -> bool {
+        !self.insufficient() && self.available_bytes < self.estimated_bytes.saturating_mul(2)
+    }
+
+    pub fn message(&self) -> String {
+        format!(
+            "This download needs about {} but only {} is free on {}",
+            human_size(self.estimated_bytes),
+            human_size(self.available_bytes),
+            self.path
+        )
+    }
+}
+
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Size of one format in bytes, from `filesize`, `filesize_approx` or, failing both,
+/// its bitrate (kbit/s) over the video's duration. The flag is set for the last two.
+fn format_size(format: &Value, duration: Option<f64>) -> Option<(u64, bool)> {
+    let field = |key: &str| format.get(key).and_then(Value::as_f64).filter(|v| *v > 0.0);
+    if let Some(size) = field("filesize") {
+        return Some((size as u64, false));
+    }
+    if let Some(size) = field("filesize_approx") {
+        return Some((size as u64, true));
+    }
+    let bytes = field("tbr")? * 1000.0 / 8.0 * duration?;
+    Some((bytes as u64, true))
+}
+
+fn find_format<'a>(info: &'a Value, id: &str) -> Option<&'a Value> {
+    info.get("formats")?
+        .as_array()?
+        .iter()
+        .find(|f| f.get("format_id").and_then(Value::as_str) == Some(id))
+}
+
+/// Expected download size of a single video from its `-J` info, either for explicitly
+/// picked formats or for the formats yt-dlp will pick for `quality`.
+/// Returns `(bytes, approximate)`, or `None` when any selected format has no size.
+pub fn estimate(info: &Value, format: Option<&FormatSelection>, quality: &str) -> Option<(u64, bool)> {
+    let duration = info.get("duration").and_then(Value::as_f64);
+    let selected: Vec<&Value> = match format {
+        Some(selection) => selection
+            .video
+            .iter()
+            .chain(selection.audio.iter())
+            .map(|id| find_format(info, id))
+            .collect::<Option<_>>()?,
+        None => match recode::selected_audio(info, quality) {
+            Some(audio) => vec![audio],
+            None => {
+                let (video, audio) = recode::selected_formats(info, quality)?;
+                std::iter::once(video).chain(audio).collect()
+            }
+        },
+    };
+
+    let mut total = 0u64;
+    let mut approximate = false;
+    for f in selected {
+        let (size, approx) = format_size(f, duration)?;
+        total = total.saturating_add(size);
+        approximate |= approx;
+    }
+    Some((total, approximate))
+}
+
+/// `path` itself when it exists, else its closest existing ancestor.
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.exists()).map(Path::to_path_buf)
+}
+
+#[cfg(unix)]
+fn free_space_at(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field widths differ between platforms.
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn free_space_at(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_to_caller: *mut u64,
+            total: *mut u64,
+            total_free: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut free = 0u64;
+    let ok = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, std::ptr::null_mut(), std::ptr::null_mut()) };
+    (ok != 0).then_some(free)
+}
+
+/// Space available to this user on the volume `path` is (or will be) on.
+pub fn free_space(path: &Path) -> Option<(u64, PathBuf)> {
+    let existing = existing_ancestor(path)?;
+    let free = free_space_at(&existing)?;
+    Some((free, existing))
+}
+
+/// Compares `estimated_bytes` with the free space for `output_dir`; `None` when the
+/// free space cannot be read.
+pub fn check(output_dir: &Path, estimated_bytes: u64, approximate: bool) -> Option<SpaceCheck> {
+    let (available_bytes, existing) = free_space(output_dir)?;
+    Some(SpaceCheck {
+        estimated_bytes,
+        available_bytes,
+        path: existing.to_string_lossy().into_owned(),
+        approximate,
+    })
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::supervisor;
+
+/// Render node used for VAAPI encoders on Linux.
+const VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// How long a single test encode may take before the encoder is considered unusable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Av1,
+}
+
+impl VideoCodec {
+    /// Codec name as understood by `recode::compatible`.
+    pub fn family(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::H265 => "h265",
+            VideoCodec::Av1 => "av1",
+        }
+    }
+
+    /// Software encoder used when no hardware encoder works.
+    pub fn software_encoder(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::H265 => "libx265",
+            VideoCodec::Av1 => "libsvtav1",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderFamily {
+    Software,
+    Nvenc,
+    Qsv,
+    Amf,
+    Vaapi,
+    VideoToolbox,
+    V4l2m2m,
+}
+
+/// Candidates in order of preference; software encoders come last.
+const CANDIDATES: &[(&str, VideoCodec, EncoderFamily)] = &[
+    ("h264_nvenc", VideoCodec::H264, EncoderFamily::Nvenc),
+    ("h264_qsv", VideoCodec::H264, EncoderFamily::Qsv),
+    ("h264_amf", VideoCodec::H264, EncoderFamily::Amf),
+    ("h264_videotoolbox", VideoCodec::H264, EncoderFamily::VideoToolbox),
+    ("h264_vaapi", VideoCodec::H264, EncoderFamily::Vaapi),
+    ("h264_v4l2m2m", VideoCodec::H264, EncoderFamily::V4l2m2m),
+    ("libx264", VideoCodec::H264, EncoderFamily::Software),
+    ("hevc_nvenc", VideoCodec::H265, EncoderFamily::Nvenc),
+    ("hevc_qsv", VideoCodec::H265, EncoderFamily::Qsv),
+    ("hevc_amf", VideoCodec::H265, EncoderFamily::Amf),
+    ("hevc_videotoolbox", VideoCodec::H265, EncoderFamily::VideoToolbox),
+    ("hevc_vaapi", VideoCodec::H265, EncoderFamily::Vaapi),
+    ("hevc_v4l2m2m", VideoCodec::H265, EncoderFamily::V4l2m2m),
+    ("libx265", VideoCodec::H265, EncoderFamily::Software),
+    ("av1_nvenc", VideoCodec::Av1, EncoderFamily::Nvenc),
+    ("av1_qsv", VideoCodec::Av1, EncoderFamily::Qsv),
+    ("av1_amf", VideoCodec::Av1, EncoderFamily::Amf),
+    ("av1_vaapi", VideoCodec::Av1, EncoderFamily::Vaapi),
+    ("libsvtav1", VideoCodec::Av1, EncoderFamily::Software),
+];
+
+/// Result of probing one encoder, returned by `get_encoders`.
+#[derive(Serialize, Clone, Debug)]
+pub struct EncoderInfo {
+    pub name: String,
+    pub codec: VideoCodec,
+    pub family: EncoderFamily,
+    pub hardware: bool,
+    /// A test encode succeeded.
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+impl EncoderInfo {
+    /// ffmpeg arguments placed before the input (device setup).
+    pub fn input_args(&self) -> Vec<String> {
+        match self.family {
+            EncoderFamily::Vaapi => vec!["-vaapi_device".into(), VAAPI_DEVICE.into()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// ffmpeg output arguments selecting this encoder.
+    pub fn output_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.family {
+            EncoderFamily::Vaapi => args.extend(["-vf".into(), "format=nv12,hwupload".into()]),
+            // Most V4L2 M2M encoders only accept NV12/YUV420P input.
+            EncoderFamily::V4l2m2m => args.extend(["-pix_fmt".into(), "nv12".into()]),
+            _ => {}
+        }
+        args.extend(["-c:v".into(), self.name.clone()]);
+        args
+    }
+}
+
+/// Encodes a fraction of a second of a generated test pattern to check that the
+/// encoder, its driver and the GPU behind it actually work.
+async fn test_encode(ffmpeg: &Path, encoder: &EncoderInfo) -> Result<(), String> {
+    let mut args: Vec<String> = ["-hide_banner", "-loglevel", "error"].iter().map(|s| s.to_string()).collect();
+    args.extend(encoder.input_args());
+    args.extend(["-f", "lavfi", "-i", "color=c=black:s=256x144:r=25:d=0.4"].iter().map(|s| s.to_string()));
+    args.extend(encoder.output_args());
+    args.extend(["-frames:v", "10", "-f", "null", "-"].iter().map(|s| s.to_string()));
+
+    let output = tokio::time::timeout(PROBE_TIMEOUT, supervisor::run(ffmpeg, &args, None))
+        .await
+        .map_err(|_| "Test encode timed out".to_string())??;
+    if output.success() {
+        Ok(())
+    } else {
+        let text = output.combined_text();
+        let last = text.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("Test encode failed");
+        Err(last.trim().to_string())
+    }
+}
+
+/// Lists every candidate encoder the bundled ffmpeg was built with and verifies each
+/// with a test encode.
+pub async fn probe(ffmpeg: &Path) -> Result<Vec<EncoderInfo>, String> {
+    let listing = supervisor::run(ffmpeg, &["-hide_banner".to_string(), "-encoders".to_string()], None).await?;
+    let listing = String::from_utf8_lossy(&listing.stdout).into_owned();
+    let built_in = |name: &str| listing.lines().any(|line| line.split_whitespace().nth(1) == Some(name));
+
+    let mut results = Vec::new();
+    for &(name, codec, family) in CANDIDATES {
+        if !built_in(name) {
+            continue;
+        }
+        let mut info = EncoderInfo {
+            name: name.to_string(),
+            codec,
+            family,
+            hardware: family != EncoderFamily::Software,
+            verified: false,
+            error: None,
+        };
+        match test_encode(ffmpeg, &info).await {
+            Ok(()) => info.verified = true,
+            Err(e) => info.error = Some(e),
+        }
+        println!("[Encoders] {} verified={} {:?}", info.name, info.verified, info.error);
+        results.push(info);
+    }
+    Ok(results)
+}
+
+/// Chooses the encoder for a transcode: the requested one if it was verified (its codec
+/// wins over `codec`), else the first verified hardware encoder for `codec`, else the
+/// software encoder.
+pub fn choose(encoders: &[EncoderInfo], codec: VideoCodec, requested: Option<&str>) -> EncoderInfo {
+    let picked = requested
+        .and_then(|name| encoders.iter().find(|e| e.verified && e.name == name))
+        .or_else(|| encoders.iter().find(|e| e.verified && e.codec == codec && e.hardware));
+    match picked {
+        Some(encoder) => encoder.clone(),
+        None => software(codec),
+    }
+}
+
+pub fn software(codec: VideoCodec) -> EncoderInfo {
+    EncoderInfo {
+        name: codec.software_encoder().to_string(),
+        codec,
+        family: EncoderFamily::Software,
+        hardware: false,
+        verified: true,
+        error: None,
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+/// Stable category of a failed download, for the UI and scripts to react to.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    LoginRequired,
+    AgeRestricted,
+    GeoBlocked,
+    /// Private, removed or otherwise gone.
+    Unavailable,
+    /// HTTP 403 from the media server.
+    Forbidden,
+    /// HTTP 429 or another "slow down" response.
+    RateLimited,
+    FormatUnavailable,
+    DiskFull,
+    NetworkTimeout,
+    FfmpegFailed,
+    Unknown,
+}
+
+/// Substrings (lowercase) of yt-dlp/ffmpeg output per category, checked in order: more
+/// specific categories come before ones whose messages they may also contain.
+const PATTERNS: &[(ErrorCode, &[&str])] = &[
+    (ErrorCode::DiskFull, &["no space left on device", "errno 28", "not enough space on the disk", "disk quota exceeded"]),
+    (ErrorCode::AgeRestricted, &["confirm your age", "age-restricted", "age restricted", "inappropriate for some users"]),
+    (ErrorCode::GeoBlocked, &[
+        "not available in your country",
+        "not made this video available in your country",
+        "geo restriction",
+        "geo-restricted",
+        "geo restricted",
+    ]),
+    (ErrorCode::Unavailable, &[
+        "private video",
+        "video unavailable",
+        "has been removed",
+        "no longer available",
+        "account associated with this video has been terminated",
+        "http error 404",
+        "does not exist",
+    ]),
+    (ErrorCode::LoginRequired, &[
+        "sign in to confirm",
+        "login required",
+        "log in to",
+        "only available for registered users",
+        "members-only",
+        "join this channel",
+        "use --cookies",
+        "requires authentication",
+    ]),
+    (ErrorCode::RateLimited, &["http error 429", "too many requests", "rate-limit", "rate limit"]),
+    (ErrorCode::Forbidden, &["http error 403", "403: forbidden"]),
+    (ErrorCode::FormatUnavailable, &["requested format is not available", "no video formats found", "format not available"]),
+    (ErrorCode::NetworkTimeout, &[
+        "timed out",
+        "connection reset",
+        "connection refused",
+        "temporary failure in name resolution",
+        "getaddrinfo failed",
+        "network is unreachable",
+        "unable to download webpage",
+        "remote end closed connection",
+    ]),
+    (ErrorCode::FfmpegFailed, &[
+        "postprocessing:",
+        "ffmpeg exited with code",
+        "ffmpeg not found",
+        "conversion failed",
+        "error opening output",
+    ]),
+];
+
+impl ErrorCode {
+    pub fn message(self) -> &'static str {
+        match self {
+            ErrorCode::LoginRequired => "The site requires you to be logged in.",
+            ErrorCode::AgeRestricted => "The video is age-restricted.",
+            ErrorCode::GeoBlocked => "The video is not available in your region.",
+            ErrorCode::Unavailable => "The video is private, removed or does not exist.",
+            ErrorCode::Forbidden => "The server refused access to the media (HTTP 403).",
+            ErrorCode::RateLimited => "The site is rate limiting requests (HTTP 429).",
+            ErrorCode::FormatUnavailable => "The requested format is not available for this video.",
+            ErrorCode::DiskFull => "The disk ran out of space.",
+            ErrorCode::NetworkTimeout => "The connection failed or timed out.",
+            ErrorCode::FfmpegFailed => "FFmpeg failed while merging or converting the file.",
+            ErrorCode::Unknown => "The download failed.",
+        }
+    }
+
+    pub fn remedy(self) -> &'static str {
+        match self {
+            ErrorCode::LoginRequired | ErrorCode::AgeRestricted => "Log in or extract browser cookies, then retry.",
+            ErrorCode::GeoBlocked => "Use a VPN or proxy in a region where the video is available.",
+            ErrorCode::Unavailable => "Check the link; the video cannot be downloaded.",
+            ErrorCode::Forbidden => "Update yt-dlp, or extract browser cookies, then retry.",
+            ErrorCode::RateLimited => "Wait a while, lower the download rate or concurrency, then retry.",
+            ErrorCode::FormatUnavailable => "Pick another quality or format.",
+            ErrorCode::DiskFull => "Free up disk space or choose another download folder.",
+            ErrorCode::NetworkTimeout => "Check your internet connection and retry.",
+            ErrorCode::FfmpegFailed => "Try another output format, or disable hardware acceleration.",
+            ErrorCode::Unknown => "See the details below; updating yt-dlp often helps.",
+        }
+    }
+}
+
+fn matching(text: &str) -> Option<ErrorCode> {
+    let text = text.to_lowercase();
+    PATTERNS
+        .iter()
+        .find(|(_, needles)| needles.iter().any(|n| text.contains(n)))
+        .map(|(code, _)| *code)
+}
+
+/// Categorizes the error output of a failed yt-dlp run. `ERROR:` lines decide first:
+/// warnings often mention problems yt-dlp worked around (e.g. a login hint).
+pub fn classify(text: &str) -> ErrorCode {
+    let errors: Vec<&str> = text.lines().filter(|l| l.trim_start().starts_with("ERROR:")).collect();
+    matching(&errors.join("\n"))
+        .or_else(|| matching(text))
+        .unwrap_or(ErrorCode::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_yt_dlp_output() {
+        let cases = [
+            ("ERROR: [youtube] abc: Sign in to confirm you're not a bot. Use --cookies-from-browser", ErrorCode::LoginRequired),
+            ("ERROR: [youtube] abc: Sign in to confirm your age. This video may be inappropriate for some users.", ErrorCode::AgeRestricted),
+            ("ERROR: [youtube] abc: The uploader has not made this video available in your country", ErrorCode::GeoBlocked),
+            ("ERROR: [youtube] abc: Private video. Sign in if you've been granted access to this video", ErrorCode::Unavailable),
+            ("ERROR: [youtube] abc: Video unavailable. This video has been removed by the uploader", ErrorCode::Unavailable),
+            ("ERROR: unable to download video data: HTTP Error 403: Forbidden", ErrorCode::Forbidden),
+            ("ERROR: [youtube] abc: HTTP Error 429: Too Many Requests", ErrorCode::RateLimited),
+            ("ERROR: [youtube] abc: Requested format is not available. Use --list-formats", ErrorCode::FormatUnavailable),
+            ("ERROR: unable to write data: [Errno 28] No space left on device", ErrorCode::DiskFull),
+            ("ERROR: [youtube] abc: Unable to download webpage: <urlopen error timed out>", ErrorCode::NetworkTimeout),
+            ("ERROR: Postprocessing: Conversion failed!", ErrorCode::FfmpegFailed),
+            ("ERROR: ffmpeg exited with code 1", ErrorCode::FfmpegFailed),
+            ("ERROR: something nobody has seen before", ErrorCode::Unknown),
+            ("", ErrorCode::Unknown),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(classify(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn error_lines_win_over_warnings() {
+        let cases = [
+            (
+                "WARNING: [youtube] No supported JavaScript runtime could be found. Use --cookies for login\n\
+                 ERROR: unable to download video data: HTTP Error 403: Forbidden",
+                ErrorCode::Forbidden,
+            ),
+            (
+                "WARNING: [youtube] Falling back to generic n function search\n\
+                 ERROR: [youtube] abc: HTTP Error 429: Too Many Requests",
+                ErrorCode::RateLimited,
+            ),
+            (
+                "[Merger] Merging formats into \"video.mp4\" with ffmpeg\n\
+                 ERROR: [youtube] abc: Video unavailable",
+                ErrorCode::Unavailable,
+            ),
+            // Without a matching ERROR line the rest of the output still counts.
+            ("WARNING: unable to download webpage: timed out\nERROR: giving up", ErrorCode::NetworkTimeout),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(classify(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn ffmpeg_mentions_alone_are_not_ffmpeg_failures() {
+        assert_eq!(classify("[Merger] Merging formats into \"a.mp4\" with ffmpeg\nERROR: boom"), ErrorCode::Unknown);
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatKind {
+    /// Video-only stream, needs an audio format to be merged in.
+    Video,
+    /// Audio-only stream.
+    Audio,
+    /// Video and audio in one file.
+    Muxed,
+}
+
+/// One entry of yt-dlp's `formats` list, normalised for the format picker.
+#[derive(Serialize, Clone, Debug)]
+pub struct FormatInfo {
+    pub id: String,
+    pub kind: FormatKind,
+    pub ext: Option<String>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub fps: Option<f64>,
+    /// `SDR`, `HDR10`, `HLG`, ... as reported by yt-dlp.
+    pub dynamic_range: Option<String>,
+    pub hdr: bool,
+    pub vcodec: Option<String>,
+    pub acodec: Option<String>,
+    pub audio_channels: Option<u64>,
+    /// Total bitrate in kbit/s.
+    pub bitrate: Option<f64>,
+    pub video_bitrate: Option<f64>,
+    pub audio_bitrate: Option<f64>,
+    /// Size in bytes; see `filesize_is_approx`.
+    pub filesize: Option<u64>,
+    pub filesize_is_approx: bool,
+    pub protocol: Option<String>,
+    pub language: Option<String>,
+    pub note: Option<String>,
+}
+
+fn text(format: &Value, key: &str) -> Option<String> {
+    format.get(key).and_then(Value::as_str).filter(|s| !s.is_empty()).map(String::from)
+}
+
+fn codec(format: &Value, key: &str) -> Option<String> {
+    text(format, key).filter(|c| c != "none")
+}
+
+fn float(format: &Value, key: &str) -> Option<f64> {
+    format.get(key).and_then(Value::as_f64)
+}
+
+fn int(format: &Value, key: &str) -> Option<u64> {
+    float(format, key).filter(|v| *v >= 0.0).map(|v| v as u64)
+}
+
+fn normalize(format: &Value) -> Option<FormatInfo> {
+    let id = text(format, "format_id")?;
+    let vcodec = codec(format, "vcodec");
+    let acodec = codec(format, "acodec");
+    let kind = match (&vcodec, &acodec) {
+        // Storyboards and other image "formats" are not downloadable media.
+        (Some(v), _) if v == "images" => return None,
+        (Some(_), Some(_)) => FormatKind::Muxed,
+        (Some(_), None) => FormatKind::Video,
+        (None, Some(_)) => FormatKind::Audio,
+        // Some extractors leave both codecs unknown for muxed progressive files.
+        (None, None) if text(format, "ext").as_deref() == Some("mhtml") => return None,
+        (None, None) => FormatKind::Muxed,
+    };
+
+    let dynamic_range = text(format, "dynamic_range");
+    let (filesize, filesize_is_approx) = match int(format, "filesize") {
+        Some(size) => (Some(size), false),
+        None => (int(format, "filesize_approx"), true),
+    };
+
+    Some(FormatInfo {
+        id,
+        kind,
+        ext: text(format, "ext"),
+        width: int(format, "width"),
+        height: int(format, "height"),
+        fps: float(format, "fps"),
+        hdr: dynamic_range.as_deref().is_some_and(|r| r != "SDR"),
+        dynamic_range,
+        vcodec,
+        acodec,
+        audio_channels: int(format, "audio_channels"),
+        bitrate: float(format, "tbr"),
+        video_bitrate: float(format, "vbr"),
+        audio_bitrate: float(format, "abr"),
+        filesize,
+        filesize_is_approx: filesize.is_some() && filesize_is_approx,
+        protocol: text(format, "protocol"),
+        language: text(format, "language"),
+        note: text(format, "format_note"),
+    })
+}
+
+/// Downloadable formats of a video's `-J` info, best first (yt-dlp lists worst first).
+pub fn list(info: &Value) -> Vec<FormatInfo> {
+    let Some(formats) = info.get("formats").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    formats.iter().rev().filter_map(normalize).collect()
+}
+
+/// Explicit format IDs picked from `list`, replacing the quality selector.
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct FormatSelection {
+    pub video: Option<String>,
+    pub audio: Option<String>,
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+impl FormatSelection {
+    /// Checks the IDs, and that they exist when the video's info is known.
+    pub fn validate(&self, info: Option<&Value>) -> Result<(), String> {
+        let ids: Vec<&String> = self.video.iter().chain(self.audio.iter()).collect();
+        if ids.is_empty() {
+            return Err("Pick at least a video or an audio format".into());
+        }
+        let known = info.map(list);
+        for id in ids {
+            if !valid_id(id) {
+                return Err(format!("Invalid format ID: {:?}", id));
+            }
+            if known.as_ref().is_some_and(|formats| !formats.iter().any(|f| &f.id == id)) {
+                return Err(format!("Format {} is not offered for this video", id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Video and audio codecs of the picked formats, looked up in the video's info.
+    pub fn codecs(&self, info: &Value) -> Option<(String, Option<String>)> {
+        let formats = list(info);
+        let find = |id: &Option<String>| id.as_ref().and_then(|id| formats.iter().find(|f| &f.id == id));
+        let video = find(&self.video)?;
+        let acodec = match find(&self.audio) {
+            Some(audio) => audio.acodec.clone(),
+            None => video.acodec.clone(),
+        };
+        Some((video.vcodec.clone()?, acodec))
+    }
+
+    /// The `-f` value: `video+audio`, or whichever one was picked.
+    pub fn selector(&self) -> String {
+        match (&self.video, &self.audio) {
+            (Some(video), Some(audio)) => format!("{}+{}", video, audio),
+            (Some(id), None) | (None, Some(id)) => id.clone(),
+            (None, None) => "best".into(),
+        }
+    }
+}
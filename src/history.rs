@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::ErrorCode;
+use crate::{storage, DownloadArgs};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStatus {
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+/// One completed, failed or cancelled download, written when its process exits.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HistoryEntry {
+    /// Job id; a resumed or retried job overwrites its earlier entry.
+    pub id: String,
+    pub url: String,
+    pub extractor: Option<String>,
+    pub video_id: Option<String>,
+    pub title: Option<String>,
+    pub download_type: String,
+    pub quality: String,
+    pub ext: Option<String>,
+    pub output_dir: String,
+    pub path: Option<String>,
+    #[serde(default)]
+    pub files: Vec<String>,
+    pub size: Option<u64>,
+    pub duration: Option<f64>,
+    pub started_at: Option<i64>,
+    pub finished_at: i64,
+    pub status: HistoryStatus,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+    /// Original request, replayed by `requeue_history_entry`.
+    pub args: DownloadArgs,
+    /// Computed on read: the recorded output file is no longer on disk.
+    #[serde(skip_deserializing)]
+    pub missing: bool,
+}
+
+impl HistoryEntry {
+    fn refresh_missing(&mut self) {
+        self.missing = self.status == HistoryStatus::Finished
+            && self.path.as_ref().map(|p| !Path::new(p).exists()).unwrap_or(true);
+    }
+
+    fn matches(&self, query: &HistoryQuery) -> bool {
+        if let Some(text) = query.text.as_ref().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()) {
+            let haystack = [
+                Some(&self.url),
+                self.title.as_ref(),
+                self.path.as_ref(),
+                self.extractor.as_ref(),
+                self.video_id.as_ref(),
+            ];
+            if !haystack.iter().flatten().any(|field| field.to_lowercase().contains(&text)) {
+                return false;
+            }
+        }
+        if query.download_type.as_ref().is_some_and(|t| *t != self.download_type) {
+            return false;
+        }
+        if query.status.is_some_and(|s| s != self.status) {
+            return false;
+        }
+        if query.extractor.as_ref().is_some_and(|e| Some(e) != self.extractor.as_ref()) {
+            return false;
+        }
+        if query.since.is_some_and(|since| self.finished_at < since) {
+            return false;
+        }
+        if query.until.is_some_and(|until| self.finished_at > until) {
+            return false;
+        }
+        if query.missing_only && !self.missing {
+            return false;
+        }
+        true
+    }
+}
+
+/// Filters for `get_history`; every field is optional.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub text: Option<String>,
+    pub download_type: Option<String>,
+    pub status: Option<HistoryStatus>,
+    pub extractor: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub missing_only: bool,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Download history persisted as `history.json`, newest entries last.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    file: Option<PathBuf>,
+}
+
+impl History {
+    pub fn load(file: &Path) -> History {
+        History { entries: storage::load_json(file), file: Some(file.to_path_buf()) }
+    }
+
+    fn save(&self) {
+        if let Some(file) = &self.file {
+            if let Err(e) = storage::save_json(file, &self.entries) {
+                println!("[History] Failed to persist history: {}", e);
+            }
+        }
+    }
+
+    pub fn record(&mut self, entry: HistoryEntry) {
+        self.entries.retain(|e| e.id != entry.id);
+        self.entries.push(entry);
+        self.save();
+    }
+
+    /// Matching entries, newest first, with `missing` freshly computed.
+    pub fn search(&self, query: &HistoryQuery) -> Vec<HistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .cloned()
+            .filter_map(|mut entry| {
+                entry.refresh_missing();
+                entry.matches(query).then_some(entry)
+            })
+            .collect()
+    }
+
+    /// Newest finished download of the same video (or URL) whose file is still on disk.
+    pub fn find_downloaded(&self, url: &str, extractor: Option<&str>, video_id: Option<&str>) -> Option<HistoryEntry> {
+        self.entries.iter().rev().find(|e| {
+            let same_video = match (extractor, video_id, e.extractor.as_deref(), e.video_id.as_deref()) {
+                (Some(x), Some(id), Some(ex), Some(eid)) => x.eq_ignore_ascii_case(ex) && id == eid,
+                _ => e.url == url,
+            };
+            same_video
+                && e.status == HistoryStatus::Finished
+                && e.path.as_ref().is_some_and(|p| Path::new(p).exists())
+        }).cloned()
+    }
+
+    pub fn get(&self, id: &str) -> Option<HistoryEntry> {
+        self.entries.iter().find(|e| e.id == id).cloned()
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.entries.retain(|e| e.id != id);
+        self.save();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn export(entries: &[HistoryEntry], path: &Path, format: ExportFormat) -> Result<(), String> {
+    let text = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?,
+        ExportFormat::Csv => {
+            let mut out = String::from(
+                "id,url,extractor,video_id,title,type,quality,ext,path,size,duration,started_at,finished_at,status,exit_code,error,missing\n",
+            );
+            for e in entries {
+                let status = match e.status {
+                    HistoryStatus::Finished => "finished",
+                    HistoryStatus::Failed => "failed",
+                    HistoryStatus::Cancelled => "cancelled",
+                };
+                let row = [
+                    e.id.clone(),
+                    e.url.clone(),
+                    e.extractor.clone().unwrap_or_default(),
+                    e.video_id.clone().unwrap_or_default(),
+                    e.title.clone().unwrap_or_default(),
+                    e.download_type.clone(),
+                    e.quality.clone(),
+                    e.ext.clone().unwrap_or_default(),
+                    e.path.clone().unwrap_or_default(),
+                    e.size.map(|v| v.to_string()).unwrap_or_default(),
+                    e.duration.map(|v| v.to_string()).unwrap_or_default(),
+                    e.started_at.map(|v| v.to_string()).unwrap_or_default(),
+                    e.finished_at.to_string(),
+                    status.to_string(),
+                    e.exit_code.map(|v| v.to_string()).unwrap_or_default(),
+                    e.error.clone().unwrap_or_default(),
+                    e.missing.to_string(),
+                ];
+                let row: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+            out
+        }
+    };
+    fs::write(path, text).map_err(|e| e.to_string())
+}
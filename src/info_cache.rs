@@ -0,0 +1,26 @@
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// How many `get_video_info` results are kept.
+const CAPACITY: usize = 16;
+
+/// Recent `get_video_info` results keyed by the URL they were fetched for, so a
+/// download request can be checked against them without running yt-dlp again.
+#[derive(Default)]
+pub struct InfoCache {
+    entries: VecDeque<(String, Value)>,
+}
+
+impl InfoCache {
+    pub fn insert(&mut self, url: &str, info: Value) {
+        self.entries.retain(|(u, _)| u != url);
+        self.entries.push_back((url.to_string(), info));
+        while self.entries.len() > CAPACITY {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Value> {
+        self.entries.iter().find(|(u, _)| u == url).map(|(_, info)| info)
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{storage, DownloadArgs};
+
+/// A yt-dlp process that was started but has not reported an exit yet.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct JournalEntry {
+    pub args: DownloadArgs,
+    /// The exact `-o` template used, so a resume lands on the same `.part` files.
+    pub output_template: String,
+    pub run: u64,
+    pub started_at: i64,
+}
+
+/// Write-ahead record of in-flight downloads, persisted as `journal.json`.
+/// Anything still listed at startup was interrupted by a crash, reboot or forced exit.
+#[derive(Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    file: Option<PathBuf>,
+}
+
+impl Journal {
+    pub fn load(file: &Path) -> Journal {
+        Journal { entries: storage::load_json(file), file: Some(file.to_path_buf()) }
+    }
+
+    fn save(&self) {
+        if let Some(file) = &self.file {
+            if let Err(e) = storage::save_json(file, &self.entries) {
+                println!("[Journal] Failed to persist journal: {}", e);
+            }
+        }
+    }
+
+    pub fn record(&mut self, args: DownloadArgs, output_template: String, run: u64) {
+        self.entries.retain(|e| e.args.id != args.id);
+        self.entries.push(JournalEntry {
+            args,
+            output_template,
+            run,
+            started_at: chrono::Utc::now().timestamp(),
+        });
+        self.save();
+    }
+
+    /// Drops the entry once its process has exited; a stale `run` means a newer
+    /// process for the same id owns the entry.
+    pub fn complete(&mut self, id: &str, run: u64) {
+        let before = self.entries.len();
+        self.entries.retain(|e| !(e.args.id == id && e.run == run));
+        if self.entries.len() != before {
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<JournalEntry> {
+        let pos = self.entries.iter().position(|e| e.args.id == id)?;
+        let entry = self.entries.remove(pos);
+        self.save();
+        Some(entry)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&JournalEntry> {
+        self.entries.iter().find(|e| e.args.id == id)
+    }
+}
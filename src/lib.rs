@@ -1,18 +1,45 @@
 use std::collections::HashMap;
-use std::sync::Mutex;
-#[cfg(windows)]
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager, WebviewWindow, Emitter};
-#[cfg(not(windows))]
-use tauri_plugin_shell::ShellExt;
-#[cfg(not(windows))]
-use tauri_plugin_shell::process::CommandEvent;
-use tauri_plugin_shell::process::CommandChild;
 use std::path::PathBuf;
 use std::fs;
 use serde::{Deserialize, Serialize};
 use system_shutdown::{shutdown, reboot, sleep, hibernate};
 
+mod archive;
+mod checksums;
+mod diskspace;
+mod encoders;
+mod errors;
+mod formats;
+mod history;
+mod info_cache;
+mod journal;
+mod limits;
+mod metadata;
+mod output;
+mod playlist;
+mod progress;
+mod presets;
+mod queue;
+mod recode;
+mod retry;
+mod schedule;
+mod sections;
+mod settings;
+mod sponsorblock;
+mod storage;
+mod subtitles;
+mod supervisor;
+mod template;
+mod transfer;
+
+use history::{History, HistoryEntry, HistoryQuery, HistoryStatus};
+use journal::Journal;
+use output::OutputTracker;
+use queue::{DownloadQueue, JobOutcome};
+use settings::Settings;
+
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 #[cfg(windows)]
@@ -20,16 +47,33 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 const APP_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36";
 
-enum ChildProcess {
-    #[allow(dead_code)]
-    Tauri(CommandChild),
-    #[cfg(windows)]
-    WindowsPid(u32),
+/// Checksum lists published next to the yt-dlp and BtbN FFmpeg release assets.
+const YTDLP_SUMS_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/SHA2-256SUMS";
+const FFMPEG_SUMS_URL: &str = "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/checksums.sha256";
+
+/// A running yt-dlp/ffmpeg process registered by the supervisor.
+struct ChildProcess {
+    pid: u32,
 }
 
 struct AppState {
     children: Mutex<HashMap<String, ChildProcess>>,
-    hw_encoder: Mutex<Option<String>>,
+    /// Probe results, filled in the background at startup or by `get_encoders`.
+    encoders: Mutex<Option<Vec<encoders::EncoderInfo>>>,
+    /// Held while probing so two probes never run at once.
+    encoder_probe: tokio::sync::Mutex<()>,
+    queue: Mutex<DownloadQueue>,
+    journal: Mutex<Journal>,
+    history: Mutex<History>,
+    settings: Mutex<Settings>,
+    /// Temporary files each job has written, kept until it finishes or is cleaned up.
+    partials: Mutex<HashMap<String, Vec<String>>>,
+    info_cache: Mutex<info_cache::InfoCache>,
+}
+
+/// Folder for the backend's own persisted state (queue, journal, history, settings).
+fn app_state_dir(app: &AppHandle) -> PathBuf {
+    app.path().local_data_dir().unwrap().join("HMD")
 }
 
 #[derive(Serialize, Clone)]
@@ -43,12 +87,57 @@ struct FinishPayload {
     id: String,
     code: Option<i32>,
     path: Option<String>,
+    /// Every produced file still on disk, including sidecar thumbnails and subtitles.
+    files: Vec<String>,
 }
 
 #[derive(Serialize, Clone)]
 struct ErrorPayload {
     id: String,
+    /// Raw yt-dlp output, for the details view.
     error: String,
+    code: errors::ErrorCode,
+    message: String,
+    remedy: String,
+}
+
+impl ErrorPayload {
+    fn new(id: String, error: String) -> Self {
+        let code = errors::classify(&error);
+        ErrorPayload { id, error, code, message: code.message().into(), remedy: code.remedy().into() }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct DiskSpaceWarning {
+    id: String,
+    message: String,
+    #[serde(flatten)]
+    check: diskspace::SpaceCheck,
+}
+
+/// Why `start_download` refused a job; `code` lets the UI react to specific failures.
+#[derive(Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+enum StartError {
+    InsufficientSpace {
+        message: String,
+        #[serde(flatten)]
+        check: diskspace::SpaceCheck,
+    },
+    Invalid { message: String },
+}
+
+impl From<String> for StartError {
+    fn from(message: String) -> Self {
+        StartError::Invalid { message }
+    }
+}
+
+impl From<&str> for StartError {
+    fn from(message: &str) -> Self {
+        StartError::Invalid { message: message.to_string() }
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -120,7 +209,8 @@ async fn check_dependencies(app: AppHandle) -> Result<serde_json::Value, String>
 
 async fn check_ytdlp_update(path: &PathBuf) -> Result<bool, String> {
     let local_hash = compute_sha256(path)?;
-    let remote_hash = fetch_latest_ytdlp_hash().await?;
+    let name = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
+    let remote_hash = checksums::published_hash(YTDLP_SUMS_URL, name).await.map_err(|e| e.to_string())?;
     Ok(local_hash.to_lowercase() != remote_hash.to_lowercase())
 }
 
@@ -141,61 +231,10 @@ fn compute_sha256(path: &PathBuf) -> Result<String, String> {
     Ok(hex::encode(hasher.finalize()))
 }
 
-async fn fetch_latest_ytdlp_hash() -> Result<String, String> {
-    let url = "https://github.com/yt-dlp/yt-dlp/releases/latest/download/SHA2-256SUMS";
-    let response = reqwest::get(url).await.map_err(|e| e.to_string())?;
-    
-    if !response.status().is_success() {
-        return Err(format!("Failed to fetch SUMS: {}", response.status()));
-    }
-
-    let text = response.text().await.map_err(|e| e.to_string())?;
-    
-    for line in text.lines() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            let filename = parts[1];
-            let target_filename = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
-            if filename.trim() == target_filename {
-                return Ok(parts[0].to_string());
-            }
-        }
-    }
-
-    Err("Hash for yt-dlp.exe not found".into())
-}
-
-async fn run_ytdlp_command(app: &AppHandle, path: &PathBuf, args: &[&str]) -> Result<(i32, String), String> {
-    #[cfg(windows)]
-    {
-        let _ = app;
-        let mut cmd = std::process::Command::new(path);
-        cmd.args(args);
-        cmd.creation_flags(CREATE_NO_WINDOW);
-        let output = cmd.output().map_err(|e| e.to_string())?;
-        let code = output.status.code().unwrap_or(-1);
-        let mut text = String::new();
-        text.push_str(&String::from_utf8_lossy(&output.stdout));
-        text.push_str(&String::from_utf8_lossy(&output.stderr));
-        return Ok((code, text));
-    }
-
-    #[cfg(not(windows))]
-    {
-        let shell = app.shell();
-        let ytdlp_str = path.to_string_lossy();
-        let output = shell
-            .command(ytdlp_str.as_ref())
-            .args(args)
-            .output()
-            .await
-            .map_err(|e: tauri_plugin_shell::Error| e.to_string())?;
-        let code = output.status.code().unwrap_or(-1);
-        let mut text = String::new();
-        text.push_str(&String::from_utf8_lossy(&output.stdout));
-        text.push_str(&String::from_utf8_lossy(&output.stderr));
-        Ok((code, text))
-    }
+async fn run_ytdlp_command(path: &std::path::Path, args: &[&str]) -> Result<(i32, String), String> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let output = supervisor::run(path, &args, None).await?;
+    Ok((output.code, output.combined_text()))
 }
 
 fn extract_version_token(output: &str, marker: &str) -> Option<String> {
@@ -232,7 +271,7 @@ fn parse_ytdlp_update_available(output: &str) -> bool {
 }
 
 async fn check_ytdlp_update_cli(app: &AppHandle, path: &PathBuf) -> Result<bool, String> {
-    let (code, output) = run_ytdlp_command(app, path, &["-vU"]).await?;
+    let (code, output) = run_ytdlp_command(path, &["-vU"]).await?;
     let _ = app.emit(
         "debug-log",
         format!("yt-dlp -vU exit code: {}\n{}", code, output),
@@ -258,7 +297,7 @@ async fn update_ytdlp(app: AppHandle) -> Result<(), String> {
         serde_json::json!({ "percent": 0.0, "details": "Updating yt-dlp..." }),
     );
 
-    let (code, output) = run_ytdlp_command(&app, &ytdlp_path, &["-U"]).await?;
+    let (code, output) = run_ytdlp_command(&ytdlp_path, &["-U"]).await?;
     let _ = app.emit(
         "debug-log",
         format!("yt-dlp -U exit code: {}\n{}", code, output),
@@ -278,6 +317,23 @@ async fn update_ytdlp(app: AppHandle) -> Result<(), String> {
 
 #[tauri::command]
 async fn get_video_info(app: AppHandle, payload: String) -> Result<serde_json::Value, String> {
+    fetch_video_info(&app, payload).await
+}
+
+/// Normalised formats of a video, for picking explicit format IDs. Uses the info
+/// fetched by `get_video_info` when available.
+#[tauri::command]
+async fn get_formats(app: AppHandle, payload: String) -> Result<Vec<formats::FormatInfo>, String> {
+    let cached = app.state::<AppState>().info_cache.lock().unwrap().get(&payload).cloned();
+    let info = match cached {
+        Some(info) => info,
+        None => fetch_video_info(&app, payload).await?,
+    };
+    Ok(formats::list(&info))
+}
+
+/// Runs `yt-dlp -J` for a URL and caches the result for later validation.
+async fn fetch_video_info(app: &AppHandle, payload: String) -> Result<serde_json::Value, String> {
     let local_app_data = app.path().local_data_dir().unwrap();
     let ytdlp_name = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
     let ytdlp_path = local_app_data.join(format!("YTDLP/{}", ytdlp_name));
@@ -303,12 +359,9 @@ async fn get_video_info(app: AppHandle, payload: String) -> Result<serde_json::V
         args.push("--no-playlist".to_string());
     }
 
-    args.push(payload);
+    args.push(payload.clone());
 
     let deno_dir = local_app_data.join("DENO");
-    let path_var = std::env::var("PATH").unwrap_or_default();
-    let path_sep = if cfg!(windows) { ";" } else { ":" };
-    let new_path = format!("{}{}{}", deno_dir.to_string_lossy(), path_sep, path_var);
 
     println!("[DEBUG] Executing Info Command:");
     println!("  Binary: {:?}", ytdlp_path);
@@ -317,36 +370,19 @@ async fn get_video_info(app: AppHandle, payload: String) -> Result<serde_json::V
     let debug_msg = format!("Info Command:\nBinary: {:?}\nArgs: {:?}", ytdlp_path, args);
     let _ = app.emit("debug-log", debug_msg);
 
-    #[cfg(windows)]
-    {
-        let mut cmd = std::process::Command::new(ytdlp_path.to_str().unwrap());
-        cmd.args(&args).env("PATH", &new_path);
-        #[cfg(windows)]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-        
-        let output = cmd.output().map_err(|e| e.to_string())?;
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-        return serde_json::from_slice(&output.stdout).map_err(|e| e.to_string());
+    let output = supervisor::run(&ytdlp_path, &args, Some(&deno_dir)).await?;
+    if !output.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
     }
+    let mut info: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
 
-    #[cfg(not(windows))]
-    {
-        let shell = app.shell();
-        let output = shell.command(ytdlp_path.to_str().unwrap())
-            .args(args)
-            .env("PATH", new_path)
-            .output()
-            .await
-            .map_err(|e: tauri_plugin_shell::Error| e.to_string())?;
-
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
-
-        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+    // Summarised next to the raw `subtitles`/`automatic_captions` maps for the UI.
+    let tracks = serde_json::to_value(subtitles::available_tracks(&info)).map_err(|e| e.to_string())?;
+    if let Some(fields) = info.as_object_mut() {
+        fields.insert("subtitle_tracks".into(), tracks);
     }
+    app.state::<AppState>().info_cache.lock().unwrap().insert(&payload, info.clone());
+    Ok(info)
 }
 
 #[tauri::command]
@@ -359,7 +395,7 @@ async fn select_folder(app: AppHandle) -> Result<Option<String>, String> {
     rx.await.map_err(|e| e.to_string())
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Clone)]
 struct DownloadArgs {
     id: String,
     url: String,
@@ -376,74 +412,170 @@ struct DownloadArgs {
     thumb_ext: Option<String>,
     #[serde(rename = "hwAccel")]
     hw_accel: Option<String>,
+    /// Display title, kept so interrupted jobs can be labelled after a restart.
+    title: Option<String>,
+    /// Continue existing `.part` files using the journaled output template.
+    #[serde(default)]
+    resume: bool,
+    /// Download again even if the archive already lists the video.
+    #[serde(default)]
+    overwrite: bool,
+    /// Download the playlist or channel at `url` in one process, into a subfolder named after `title`.
+    #[serde(default)]
+    playlist: bool,
+    /// `--playlist-items` selection (e.g. "1,3,5-7"); every entry when absent.
+    #[serde(rename = "playlistItems")]
+    playlist_items: Option<String>,
+    #[serde(default)]
+    subtitles: subtitles::SubtitleOptions,
+    /// Overrides the embedding defaults from settings for this job.
+    embed: Option<metadata::EmbedOptions>,
+    sponsorblock: Option<sponsorblock::SponsorBlockOptions>,
+    /// Download only these parts of the video.
+    #[serde(default)]
+    sections: sections::SectionOptions,
+    /// Encoding preset for transcodes; the default from settings when unset.
+    preset: Option<String>,
+    /// Explicit format IDs; `quality` is used as the format selector when unset.
+    format: Option<formats::FormatSelection>,
+    /// Bandwidth limits for this job; can only lower the global rate limit.
+    limits: Option<limits::TransferLimits>,
+    /// Run number within the retry policy; 0 or 1 for the first run.
+    #[serde(default)]
+    attempt: u32,
 }
 
-fn detect_best_hw_encoder(ffmpeg_path: &PathBuf) -> Option<String> {
-    #[cfg(windows)]
-    use std::os::windows::process::CommandExt;
-    #[cfg(windows)]
-    const CREATE_NO_WINDOW: u32 = 0x08000000;
-
-    let mut cmd = std::process::Command::new(ffmpeg_path);
-    cmd.arg("-encoders");
-    
-    #[cfg(windows)]
-    cmd.creation_flags(CREATE_NO_WINDOW);
+impl DownloadArgs {
+    /// Folder the files end up in: the output folder, or its playlist subfolder.
+    fn target_dir(&self) -> String {
+        if self.playlist {
+            let folder = playlist::folder_name(self.title.as_deref().unwrap_or_default());
+            std::path::Path::new(&self.output_dir).join(folder).to_string_lossy().into_owned()
+        } else {
+            self.output_dir.clone()
+        }
+    }
+}
 
-    let output = cmd.output().ok()?;
-        
-    let encoders = String::from_utf8_lossy(&output.stdout);
-    
-    if encoders.contains("h264_nvenc") {
-        return Some("h264_nvenc".to_string());
+/// Cached encoder probe results, probing on first use. A caller arriving while a probe
+/// runs waits for its results instead of starting another one.
+async fn probed_encoders(app: &AppHandle, ffmpeg_path: &std::path::Path, refresh: bool) -> Result<Vec<encoders::EncoderInfo>, String> {
+    let state = app.state::<AppState>();
+    let _probing = state.encoder_probe.lock().await;
+    if !refresh {
+        if let Some(cached) = state.encoders.lock().unwrap().clone() {
+            return Ok(cached);
+        }
     }
-    if encoders.contains("h264_qsv") {
-        return Some("h264_qsv".to_string());
+    let _ = app.emit("debug-log", "Probing video encoders...".to_string());
+    let results = encoders::probe(ffmpeg_path).await?;
+    *state.encoders.lock().unwrap() = Some(results.clone());
+    Ok(results)
+}
+
+/// Probes the encoders in the background so that downloads never wait for the test
+/// encodes; until it finishes, recodes use the software encoder.
+fn start_encoder_probe(app: &AppHandle) {
+    let ffmpeg_name = if cfg!(windows) { "bin/ffmpeg.exe" } else { "bin/ffmpeg" };
+    let ffmpeg_path = app.path().local_data_dir().unwrap().join("FFMPEG").join(ffmpeg_name);
+    if !ffmpeg_path.exists() {
+        return;
     }
-    if encoders.contains("h264_amf") {
-        return Some("h264_amf".to_string());
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match probed_encoders(&app, &ffmpeg_path, false).await {
+            Ok(found) => println!("[Encoders] {} encoder(s) verified", found.iter().filter(|e| e.verified).count()),
+            Err(e) => {
+                println!("[Encoders] Probe failed: {}", e);
+                let _ = app.emit("debug-log", format!("Encoder probe failed: {}", e));
+            }
+        }
+    });
+}
+
+/// Lists candidate encoders with their test-encode results; `payload` forces a re-probe.
+#[tauri::command]
+async fn get_encoders(app: AppHandle, payload: Option<bool>) -> Result<Vec<encoders::EncoderInfo>, String> {
+    let ffmpeg_name = if cfg!(windows) { "bin/ffmpeg.exe" } else { "bin/ffmpeg" };
+    let ffmpeg_path = app.path().local_data_dir().unwrap().join("FFMPEG").join(ffmpeg_name);
+    if !ffmpeg_path.exists() {
+        return Err("FFmpeg not found. Please re-download dependencies.".into());
     }
-    
-    None
+    probed_encoders(&app, &ffmpeg_path, payload.unwrap_or(false)).await
 }
 
 
+/// Validates the request and hands it to the backend queue, which starts it
+/// as soon as a concurrency slot is free.
 #[tauri::command]
-async fn start_download(app: AppHandle, payload: DownloadArgs, state: tauri::State<'_, AppState>) -> Result<(), String> {
+async fn start_download(app: AppHandle, payload: DownloadArgs, state: tauri::State<'_, AppState>) -> Result<(), StartError> {
     let local_app_data = app.path().local_data_dir().unwrap();
-    
-    let (ytdlp_name, ffmpeg_name) = if cfg!(windows) {
-        ("yt-dlp.exe", "bin/ffmpeg.exe")
-    } else {
-        ("yt-dlp", "bin/ffmpeg")
-    };
-
-    let ytdlp_path = local_app_data.join("YTDLP").join(ytdlp_name);
+    let ffmpeg_name = if cfg!(windows) { "bin/ffmpeg.exe" } else { "bin/ffmpeg" };
     let ffmpeg_path = local_app_data.join("FFMPEG").join(ffmpeg_name);
 
-    // Check cache for hw_encoder
-    let mut cached_encoder = None;
-    {
-        let cache = state.hw_encoder.lock().unwrap();
-        if cache.is_some() {
-            cached_encoder = cache.clone();
-        }
+    if !ffmpeg_path.exists() {
+        return Err("FFmpeg not found. Please re-download dependencies.".into());
     }
 
-    if cached_encoder.is_none() {
-        if let Some(encoder) = detect_best_hw_encoder(&ffmpeg_path) {
-            let mut cache = state.hw_encoder.lock().unwrap();
-            *cache = Some(encoder.clone());
-            cached_encoder = Some(encoder);
+    if let Some(items) = &payload.playlist_items {
+        playlist::validate_items(items)?;
+    }
+    payload.subtitles.validate()?;
+    if let Some(embed) = &payload.embed {
+        embed.validate()?;
+    }
+    if let Some(sponsorblock) = &payload.sponsorblock {
+        sponsorblock.validate()?;
+    }
+    if let Some(limits) = &payload.limits {
+        limits.validate()?;
+    }
+    if let Some(format) = &payload.format {
+        if payload.playlist {
+            return Err("Explicit formats can only be picked for single videos".into());
         }
+        let cache = state.info_cache.lock().unwrap();
+        format.validate(cache.get(&payload.url))?;
+    }
+    if !payload.sections.is_empty() {
+        if payload.playlist {
+            return Err("Sections can only be selected for single videos".into());
+        }
+        let cache = state.info_cache.lock().unwrap();
+        payload.sections.validate(cache.get(&payload.url))?;
     }
 
-    if !ffmpeg_path.exists() {
-        return Err("FFmpeg not found. Please re-download dependencies.".into());
+    let output_path = std::path::PathBuf::from(&payload.output_dir);
+
+    // Only single, whole videos with cached info can be estimated; resumed jobs already
+    // have part of the data on disk.
+    if !payload.playlist && payload.sections.is_empty() && !payload.resume {
+        match space_check(&app, &payload) {
+            Some(check) if check.insufficient() => {
+                let message = check.message();
+                println!("[ERROR] {}", message);
+                let _ = app.emit("debug-log", format!("ERROR: {}", message));
+                return Err(StartError::InsufficientSpace { message, check });
+            }
+            Some(check) if check.tight() => {
+                let message = format!("{}; merging or converting may run out of space", check.message());
+                let _ = app.emit("debug-log", format!("WARNING: {}", message));
+                let _ = app.emit("disk-space-warning", DiskSpaceWarning { id: payload.id.clone(), message, check });
+            }
+            Some(check) => {
+                let _ = app.emit("debug-log", format!(
+                    "Estimated size {} ({} free)",
+                    diskspace::human_size(check.estimated_bytes),
+                    diskspace::human_size(check.available_bytes)
+                ));
+            }
+            None => {
+                let _ = app.emit("debug-log", "Output size unknown, skipping disk space check".to_string());
+            }
+        }
     }
 
     // Explicitly create output directory to prevent yt-dlp failure on mixed paths/Arabic chars
-    let output_path = std::path::PathBuf::from(&payload.output_dir);
     if !output_path.exists() {
         let msg = format!("Creating directory: {:?}", output_path);
         println!("[DEBUG] {}", msg);
@@ -453,58 +585,216 @@ async fn start_download(app: AppHandle, payload: DownloadArgs, state: tauri::Sta
              let err_msg = format!("Failed to create output directory: {}", e);
              println!("[ERROR] {}", err_msg);
              let _ = app.emit("debug-log", format!("ERROR: {}", err_msg));
-             return Err(err_msg);
+             return Err(err_msg.into());
         }
     } else {
         let _ = app.emit("debug-log", format!("Directory exists: {:?}", output_path));
     }
 
-    let output_template = if cfg!(windows) {
-        format!("{}\\%(title)s [%(id)s].%(ext)s", payload.output_dir.replace("/", "\\"))
+    state.queue.lock().unwrap().enqueue(payload)?;
+    queue::pump(&app);
+    Ok(())
+}
+
+/// Estimates a job from its cached info and compares that with the free space of its
+/// output folder; `None` when the size or the free space is unknown.
+fn space_check(app: &AppHandle, payload: &DownloadArgs) -> Option<diskspace::SpaceCheck> {
+    let (bytes, approximate) = {
+        let state = app.state::<AppState>();
+        let cache = state.info_cache.lock().unwrap();
+        diskspace::estimate(cache.get(&payload.url)?, payload.format.as_ref(), &payload.quality)?
+    };
+    diskspace::check(std::path::Path::new(&payload.output_dir), bytes, approximate)
+}
+
+/// Spawns yt-dlp for a job the queue has marked active.
+async fn launch_download(app: &AppHandle, payload: DownloadArgs, run: u64) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let local_app_data = app.path().local_data_dir().unwrap();
+    
+    let (ytdlp_name, ffmpeg_name) = if cfg!(windows) {
+        ("yt-dlp.exe", "bin/ffmpeg.exe")
     } else {
-        format!("{}/%(title)s [%(id)s].%(ext)s", payload.output_dir)
+        ("yt-dlp", "bin/ffmpeg")
     };
 
+    let ytdlp_path = local_app_data.join("YTDLP").join(ytdlp_name);
+    let ffmpeg_path = local_app_data.join("FFMPEG").join(ffmpeg_name);
+
+    if !ffmpeg_path.exists() {
+        return Err("FFmpeg not found. Please re-download dependencies.".into());
+    }
+
+    // Other downloads may have used up the space while this one waited in the queue.
+    if !payload.playlist && payload.sections.is_empty() && !payload.resume {
+        if let Some(check) = space_check(app, &payload).filter(|c| c.insufficient()) {
+            return Err(format!("Not enough space on the disk. {}", check.message()));
+        }
+    }
+
+    let journaled_template = if payload.resume {
+        state.journal.lock().unwrap().get(&payload.id).map(|e| e.output_template.clone())
+    } else {
+        None
+    };
+    let output_template = journaled_template.unwrap_or_else(|| {
+        let mut template = state.settings.lock().unwrap().output_template.clone();
+        if !payload.sections.is_empty() {
+            template = sections::with_section_suffix(&template);
+        }
+        if cfg!(windows) {
+            format!("{}\\{}", payload.target_dir().replace("/", "\\"), template)
+        } else {
+            format!("{}/{}", payload.target_dir(), template)
+        }
+    });
+    let job_args = payload.clone();
+    let retry_args = payload.clone();
+    let target_dir = payload.target_dir();
+    // Hardware encoder used for a transcode, if any; a failure falls back to software.
+    let mut hw_encoder: Option<String> = None;
+
     let mut args = vec![
-        "--ignore-config".into(), "--progress".into(), "--no-playlist".into(),
+        "--ignore-config".into(), "--progress".into(),
         "--encoding".into(), "utf-8".into(), "--newline".into(),
         "--geo-bypass".into(), "--no-mtime".into(),
         "--force-ipv4".into(), // Faster DNS/handshake in some environments
-        "--concurrent-fragments".into(), "5".into(), // Speed up fragments
         "--user-agent".into(), APP_USER_AGENT.into(),
-        "-o".into(), output_template,
+        "-o".into(), output_template.clone(),
     ];
 
+    let transfer = {
+        let settings = state.settings.lock().unwrap();
+        let window_rate = schedule::current(&settings.schedule)
+            .filter(|w| w.action == schedule::WindowAction::Throttle)
+            .and_then(|w| w.rate_limit);
+        limits::effective(&settings.limits, payload.limits.as_ref(), window_rate.as_deref(), settings.max_concurrent)
+    };
+    transfer.push_args(&mut args);
+
+    if payload.playlist {
+        // Keep going past unavailable/private entries; each failure is reported separately.
+        args.extend(["--yes-playlist".into(), "--ignore-errors".into()]);
+        if let Some(items) = &payload.playlist_items {
+            args.extend(["--playlist-items".into(), items.clone()]);
+        }
+    } else {
+        args.push("--no-playlist".into());
+    }
+
+    if payload.overwrite {
+        args.push("--force-overwrites".into());
+    } else {
+        let archive_mode = state.settings.lock().unwrap().archive_mode;
+        if let Some(archive) = archive::archive_file(archive_mode, &app_state_dir(app), &payload.output_dir) {
+            args.push("--download-archive".into());
+            args.push(archive.to_string_lossy().into_owned());
+        }
+    }
+
+    payload.sections.push_args(&mut args);
+    payload.subtitles.push_args(&payload.download_type, &mut args);
+
+    let embed = payload.embed.clone().unwrap_or_else(|| state.settings.lock().unwrap().embed.clone());
+    let container = match payload.download_type.as_str() {
+        "audio" => payload.audio_ext.clone().unwrap_or_else(|| "mp3".into()),
+        _ => payload.video_ext.clone().unwrap_or_else(|| "mp4".into()),
+    };
+    embed.push_args(&payload.download_type, &container, &mut args);
+
+    if let Some(sponsorblock) = &payload.sponsorblock {
+        let api = state.settings.lock().unwrap().sponsorblock_api.clone();
+        sponsorblock.push_args(&payload.download_type, api.as_deref(), &mut args);
+    }
+
+    args.extend(progress::template_args());
+    args.extend(output::print_args());
+
+    if payload.resume {
+        args.push("--continue".into());
+    }
+
     let cookies_path = local_app_data.join("cookies.txt");
     if cookies_path.exists() {
         println!("Using cookies from: {:?}", cookies_path);
         args.push("--cookies".into());
         args.push(cookies_path.to_string_lossy().into_owned());
     }
+    let retry_policy = state.settings.lock().unwrap().retry.clone();
+    retry_policy.push_args(payload.attempt.max(1), cookies_path.exists(), &mut args);
 
     match payload.download_type.as_str() {
         "video" => {
-            // Always use the quality string from the payload for the -f flag.
-            // This ensures resolution limits (e.g. [height<=720]) are honored.
-            if !payload.quality.contains("+") && !payload.quality.contains("/") {
-                args.extend(["-f".into(), format!("{}+bestaudio/best", payload.quality)]);
-            } else {
-                args.extend(["-f".into(), payload.quality.clone()]);
+            match &payload.format {
+                Some(format) => args.extend(["-f".into(), format.selector()]),
+                None => {
+                    // The quality string is a complete selector, so resolution limits
+                    // (e.g. [height<=720]) are honored.
+                    args.extend(["-f".into(), payload.quality.clone()]);
+
+                    // Use -S (sort) to prefer resolution first, then codecs like H264 and AAC.
+                    // This ensures we get the highest quality even if it's in a format like VP9/AV1.
+                    args.extend(["-S".into(), "res,vcodec:h264,acodec:aac".into()]);
+                }
             }
 
-            // Use -S (sort) to prefer resolution first, then codecs like H264 and AAC.
-            // This ensures we get the highest quality even if it's in a format like VP9/AV1.
-            args.extend(["-S".into(), "res,vcodec:h264,acodec:aac".into()]);
-
             let video_ext = payload.video_ext.clone().unwrap_or_else(|| "mp4".into());
-            // Always recode to the target extension to ensure compatibility regardless of source codec.
-            args.extend(["--recode-video".into(), video_ext.clone()]);
-            args.extend(["--merge-output-format".into(), video_ext.clone()]);
-
-            if payload.hw_accel.as_deref() == Some("auto") {
-                if let Some(encoder) = cached_encoder {
-                     let ppa = format!("VideoConvertor:-vcodec {}", encoder);
-                     args.extend(["--postprocessor-args".into(), ppa]);
+
+            // Only transcode when the selected streams can't simply be copied into the target container.
+            let policy = state.settings.lock().unwrap().recode_policy;
+            let conversion = {
+                let cache = state.info_cache.lock().unwrap();
+                let selected = cache.get(&payload.url).and_then(|info| match &payload.format {
+                    Some(format) => format.codecs(info),
+                    None => recode::selected_codecs(info, &payload.quality),
+                });
+                recode::decide(policy, selected, &video_ext)
+            };
+            args.extend(recode::container_args(conversion, &video_ext));
+            match conversion {
+                recode::Conversion::Remux => {}
+                recode::Conversion::Recode => {
+                    let (codec, preferred, preset) = {
+                        let settings = state.settings.lock().unwrap();
+                        let preset_name = payload.preset.as_deref().unwrap_or(&settings.encoding_preset);
+                        (settings.video_codec, settings.encoder.clone(), presets::find(&settings.custom_presets, preset_name))
+                    };
+                    // "none" forces the CPU, "auto" uses the preference, anything else names an encoder.
+                    let (requested, hardware) = match payload.hw_accel.as_deref() {
+                        Some("none") => (None, false),
+                        Some("auto") | None => (preferred, true),
+                        Some(name) => (Some(name.to_string()), true),
+                    };
+                    let encoder = if hardware {
+                        let probed = state.encoders.lock().unwrap().clone();
+                        if probed.is_none() {
+                            println!("[Encoders] Probe not finished yet, using the software encoder");
+                        }
+                        encoders::choose(&probed.unwrap_or_default(), codec, requested.as_deref())
+                    } else {
+                        encoders::software(codec)
+                    };
+                    if !recode::compatible(&video_ext, encoder.codec.family(), None) {
+                        println!("[Encoders] {:?} cannot go into .{}, leaving the encoder to ffmpeg", encoder.codec, video_ext);
+                    } else {
+                        let mut preset = preset;
+                        let audio_family = preset.audio_codec.and_then(|c| c.family());
+                        if !recode::compatible(&video_ext, encoder.codec.family(), audio_family) {
+                            println!("[Encoders] {:?} audio cannot go into .{}, leaving the audio codec to ffmpeg", preset.audio_codec, video_ext);
+                            preset.audio_codec = None;
+                        }
+                        let input_args = encoder.input_args();
+                        if !input_args.is_empty() {
+                            args.extend(["--postprocessor-args".into(), format!("VideoConvertor+ffmpeg_i:{}", input_args.join(" "))]);
+                        }
+                        let mut output_args = encoder.output_args();
+                        output_args.extend(preset.video_args(&encoder));
+                        output_args.extend(preset.audio_args());
+                        args.extend(["--postprocessor-args".into(), format!("VideoConvertor+ffmpeg_o:{}", output_args.join(" "))]);
+                        if encoder.hardware {
+                            hw_encoder = Some(encoder.name.clone());
+                        }
+                    }
                 }
             }
 
@@ -512,6 +802,9 @@ async fn start_download(app: AppHandle, payload: DownloadArgs, state: tauri::Sta
         }
         "audio" => {
             let audio_ext = payload.audio_ext.unwrap_or_else(|| "mp3".into());
+            if let Some(format) = &payload.format {
+                args.extend(["-f".into(), format.selector()]);
+            }
             args.extend(["-x".into(), "--audio-format".into(), audio_ext, "--audio-quality".into(), payload.quality,
                 "--ffmpeg-location".into(), ffmpeg_path.parent().unwrap().to_str().unwrap().into()]);
         }
@@ -527,303 +820,531 @@ async fn start_download(app: AppHandle, payload: DownloadArgs, state: tauri::Sta
 
     // Re-verify binary existence (optional but safe)
     if !ytdlp_path.exists() {
-         let _ = app.emit("download-error", ErrorPayload { id: payload.id.clone(), error: "yt-dlp binary not found".into() });
-         return Err("yt-dlp missing".into());
+         return Err("yt-dlp binary not found".into());
     }
 
-    // ... (args construction matches existing code) ...
-
     let deno_dir = local_app_data.join("DENO");
-    let path_var = std::env::var("PATH").unwrap_or_default();
-    let new_path = format!("{};{}", deno_dir.to_string_lossy(), path_var);
 
     let debug_msg = format!("Binary: {:?}\nArgs: {:?}", ytdlp_path, args);
     println!("[DEBUG] {}", debug_msg);
     let _ = app.emit("debug-log", debug_msg);
 
-    let app_clone = app.clone();
     let download_id = payload.id.clone();
+    let tracker = Arc::new(Mutex::new(OutputTracker::default()));
+
+    let app_lines = app.clone();
+    let id_lines = download_id.clone();
+    let tracker_lines = tracker.clone();
+    let is_playlist = payload.playlist;
+    let process = supervisor::spawn(app, &download_id, &ytdlp_path, &args, Some(&deno_dir), move |line| match line {
+        supervisor::Line::Stdout(text) => forward_stdout_line(&app_lines, &id_lines, text, &tracker_lines),
+        supervisor::Line::Stderr(text) => {
+            // `--print` implies `--quiet`, which moves our progress-template lines to stderr.
+            if let Some(progress) = progress::parse_line(&id_lines, &text) {
+                forward_progress(&app_lines, &id_lines, progress);
+                return;
+            }
+            println!("[YT-DLP STDERR] {}", text);
+            if is_playlist {
+                report_entry_error(&app_lines, &id_lines, &text, &tracker_lines);
+            }
+            let _ = app_lines.emit("ytdlp-output", ProgressPayload { id: id_lines.clone(), data: format!("[STDERR] {}", text) });
+        }
+    })?;
+    state.journal.lock().unwrap().record(job_args, output_template, run);
 
-    #[cfg(windows)]
-    {
-        let mut cmd = tokio::process::Command::new(ytdlp_path.to_str().unwrap());
-        cmd.args(&args)
-           .env("PATH", &new_path);
-        
-        #[cfg(windows)]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-        
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::piped());
-        cmd.stdin(std::process::Stdio::null());
-
-        let mut child = cmd.spawn().map_err(|e| e.to_string())?;
-        let stdout = child.stdout.take().unwrap();
-        let stderr = child.stderr.take().unwrap();
-        let pid = child.id().unwrap_or(0);
-        
-        state.children.lock().unwrap().insert(download_id.clone(), ChildProcess::WindowsPid(pid));
-
-        // Shared state for capturing output
-        let stdout_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let error_lines = Arc::new(Mutex::new(Vec::<String>::new()));
-        let captured_path = Arc::new(Mutex::new(None::<String>));
-
-        // Background task to read stdout - returns JoinHandle
-        let app_stdout = app_clone.clone();
-        let id_stdout = download_id.clone();
-        let stdout_lines_clone = stdout_lines.clone();
-        let captured_path_clone = captured_path.clone();
-        let stdout_id_debug = download_id.clone();
-        let stdout_handle = tauri::async_runtime::spawn(async move {
-            println!("[DEBUG] Stdout reader started for {}", stdout_id_debug);
-            use tokio::io::AsyncReadExt;
-            let mut buffer = vec![0u8; 4096];
-            let mut stdout = stdout;
-            let mut line_count = 0;
-            let mut partial_line = String::new();
-            
-            loop {
-                match stdout.read(&mut buffer).await {
-                    Ok(0) => {
-                        // EOF - process any remaining partial line
-                        if !partial_line.trim().is_empty() {
-                            let mut lines = stdout_lines_clone.lock().unwrap();
-                            lines.push(partial_line.clone());
-                            if lines.len() > 15 { lines.remove(0); }
-                            println!("[YT-DLP STDOUT] {}", partial_line);
-                            let _ = app_stdout.emit("ytdlp-output", ProgressPayload { id: id_stdout.clone(), data: partial_line });
-                        }
-                        println!("[DEBUG] Stdout reader finished for {} after {} lines", stdout_id_debug, line_count);
-                        break;
-                    }
-                    Ok(n) => {
-                        // Convert bytes to UTF-8 with lossy conversion
-                        let chunk = String::from_utf8_lossy(&buffer[..n]);
-                        partial_line.push_str(&chunk);
-                        
-                        // Process complete lines
-                        while let Some(newline_pos) = partial_line.find('\n') {
-                            let line: String = partial_line.drain(..=newline_pos).collect();
-                            let line = line.trim_end_matches(|c| c == '\n' || c == '\r').to_string();
-                            line_count += 1;
-                            
-                            if !line.trim().is_empty() {
-                                let mut lines = stdout_lines_clone.lock().unwrap();
-                                lines.push(line.clone());
-                                if lines.len() > 15 { lines.remove(0); }
-
-                                // Capture final path
-                                // [download] Destination: ... or [Merger] Merging formats into "..."
-                                if line.contains("[download] Destination:") {
-                                    if let Some(pos) = line.find("Destination: ") {
-                                        let path = line[pos + 13..].trim().to_string();
-                                        *captured_path_clone.lock().unwrap() = Some(path);
-                                    }
-                                } else if line.contains("Merging formats into \"") {
-                                    if let Some(start) = line.find('"') {
-                                        if let Some(end) = line.rfind('"') {
-                                            if end > start {
-                                                let path = line[start + 1..end].to_string();
-                                                *captured_path_clone.lock().unwrap() = Some(path);
-                                            }
-                                        }
-                                    }
-                                } else if line.contains("[ExtractAudio] Destination:") {
-                                     if let Some(pos) = line.find("Destination: ") {
-                                        let path = line[pos + 13..].trim().to_string();
-                                        *captured_path_clone.lock().unwrap() = Some(path);
-                                    }
-                                }
-                            }
-                            println!("[YT-DLP STDOUT] {}", line);
-                            let _ = app_stdout.emit("ytdlp-output", ProgressPayload { id: id_stdout.clone(), data: line });
-                        }
-                    }
-                    Err(e) => {
-                        println!("[DEBUG] Stdout reader error for {}: {}", stdout_id_debug, e);
-                        break;
-                    }
+    // A pause or cancel that arrived while the process was starting had nothing to kill.
+    if state.queue.lock().unwrap().status(&download_id, run) != Some(queue::JobStatus::Active) {
+        if let Some(child) = state.children.lock().unwrap().get(&download_id) {
+            supervisor::kill(child.pid);
+        }
+    }
+
+    let app_term = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let exit = match process.await {
+            Ok(exit) => exit,
+            Err(e) => supervisor::Exit { code: None, stdout_tail: Vec::new(), stderr_tail: Vec::new(), wait_error: Some(e.to_string()) },
+        };
+
+        // A paused job keeps its journal entry and partial files for the resume.
+        let status = app_term.state::<AppState>().queue.lock().unwrap().status(&download_id, run);
+        if status == Some(queue::JobStatus::Paused) {
+            println!("[Queue] Download {} paused", download_id);
+            let _ = app_term.emit("download-paused", download_id.clone());
+            queue::pump(&app_term);
+            return;
+        }
+        // Cancelled while running: `cancel_download` already told the UI, so the exit is
+        // neither an error nor a reason to retry. Only the journal and history are left.
+        if status != Some(queue::JobStatus::Active) {
+            let state = app_term.state::<AppState>();
+            state.journal.lock().unwrap().complete(&download_id, run);
+            let job = state.queue.lock().unwrap().job(&download_id).filter(|j| j.run == run);
+            if let Some(job) = job {
+                record_history(&app_term, &job, exit.code, Some(&tracker.lock().unwrap()));
+            }
+            return;
+        }
+
+        // With --ignore-errors a playlist exits with 1 when some entries failed; that is
+        // still a finished job as long as anything was downloaded.
+        let succeeded = exit.success()
+            || (is_playlist && exit.code == Some(1) && tracker.lock().unwrap().produced_media());
+
+        // A hardware encoder that passed the probe can still fail on real input (driver
+        // limits, unsupported resolution). Retry the transcode on the CPU; yt-dlp skips
+        // the download itself because the merged file is already there.
+        if let Some(encoder) = hw_encoder.filter(|_| !succeeded && exit.error_message().contains("Postprocessing")) {
+            let msg = format!("Hardware encoder {} failed, retrying with software encoding", encoder);
+            println!("[Encoders] {}", msg);
+            let _ = app_term.emit("debug-log", msg);
+            if let Some(probed) = app_term.state::<AppState>().encoders.lock().unwrap().as_mut() {
+                if let Some(info) = probed.iter_mut().find(|e| e.name == encoder) {
+                    info.verified = false;
+                    info.error = Some("Failed during a download".into());
                 }
             }
-        });
-
-        // Background task to read stderr - returns JoinHandle
-        let app_stderr = app_clone.clone();
-        let id_stderr = download_id.clone();
-        let error_lines_clone = error_lines.clone();
-        let stderr_id_debug = download_id.clone();
-        let stderr_handle = tauri::async_runtime::spawn(async move {
-            println!("[DEBUG] Stderr reader started for {}", stderr_id_debug);
-            use tokio::io::AsyncReadExt;
-            let mut buffer = vec![0u8; 4096];
-            let mut stderr = stderr;
-            let mut line_count = 0;
-            let mut partial_line = String::new();
-            
-            loop {
-                match stderr.read(&mut buffer).await {
-                    Ok(0) => {
-                        // EOF - process any remaining partial line
-                        if !partial_line.trim().is_empty() {
-                            let mut lines = error_lines_clone.lock().unwrap();
-                            lines.push(partial_line.clone());
-                            if lines.len() > 15 { lines.remove(0); }
-                            println!("[YT-DLP STDERR] {}", partial_line);
-                            let formatted = format!("[STDERR] {}", partial_line);
-                            let _ = app_stderr.emit("ytdlp-output", ProgressPayload { id: id_stderr.clone(), data: formatted });
-                        }
-                        println!("[DEBUG] Stderr reader finished for {} after {} lines", stderr_id_debug, line_count);
-                        break;
+            let mut fallback = retry_args;
+            fallback.hw_accel = Some("none".into());
+            fallback.resume = true;
+            queue::run_job(&app_term, fallback, run);
+            return;
+        }
+
+        if !succeeded {
+            let error_msg = exit.error_message();
+            let code = errors::classify(&error_msg);
+            let policy = app_term.state::<AppState>().settings.lock().unwrap().retry.clone();
+            let attempt = retry_args.attempt.max(1);
+            if policy.should_retry(code, attempt) {
+                let notice = retry::Retrying {
+                    id: download_id.clone(),
+                    attempt: attempt + 1,
+                    max_attempts: policy.max_attempts,
+                    code,
+                    reason: code.message().into(),
+                    delay_secs: policy.delay(attempt + 1).as_secs(),
+                };
+                let msg = format!("Download {} failed ({:?}), retrying in {}s (attempt {}/{})", download_id, code, notice.delay_secs, notice.attempt, notice.max_attempts);
+                println!("[Retry] {}", msg);
+                let _ = app_term.emit("debug-log", msg);
+                let _ = app_term.emit("download-retrying", notice);
+
+                tokio::time::sleep(policy.delay(attempt + 1)).await;
+                // The job keeps its slot while waiting; a pause or cancel in the meantime wins.
+                let status = app_term.state::<AppState>().queue.lock().unwrap().status(&download_id, run);
+                match status {
+                    Some(queue::JobStatus::Active) => {
+                        let mut again = retry_args;
+                        again.attempt = attempt + 1;
+                        again.resume = true;
+                        queue::run_job(&app_term, again, run);
                     }
-                    Ok(n) => {
-                        // Convert bytes to UTF-8 with lossy conversion
-                        let chunk = String::from_utf8_lossy(&buffer[..n]);
-                        partial_line.push_str(&chunk);
-                        
-                        // Process complete lines
-                        while let Some(newline_pos) = partial_line.find('\n') {
-                            let line: String = partial_line.drain(..=newline_pos).collect();
-                            let line = line.trim_end_matches(|c| c == '\n' || c == '\r').to_string();
-                            line_count += 1;
-                            
-                            if !line.trim().is_empty() {
-                                let mut lines = error_lines_clone.lock().unwrap();
-                                lines.push(line.clone());
-                                if lines.len() > 15 { lines.remove(0); }
-                            }
-                            println!("[YT-DLP STDERR] {}", line);
-                            let formatted = format!("[STDERR] {}", line);
-                            let _ = app_stderr.emit("ytdlp-output", ProgressPayload { id: id_stderr.clone(), data: formatted });
+                    Some(queue::JobStatus::Paused) => {}
+                    _ => {
+                        if let Some(job) = queue::finish(&app_term, &download_id, run, JobOutcome::Failed(error_msg)) {
+                            record_history(&app_term, &job, exit.code, Some(&tracker.lock().unwrap()));
                         }
                     }
-                    Err(e) => {
-                        println!("[DEBUG] Stderr reader error for {}: {}", stderr_id_debug, e);
-                        break;
-                    }
-                }
-            }
-        });
-
-        // Main task: wait for child AND reader tasks to complete
-        let app_term = app_clone.clone();
-        let id_term = download_id.clone();
-        tauri::async_runtime::spawn(async move {
-            // Wait for the process to exit
-            let status = child.wait().await;
-            
-            // CRITICAL: Wait for reader tasks to complete so all output is captured
-            let _ = stdout_handle.await;
-            let _ = stderr_handle.await;
-            
-            match status {
-                Ok(s) => {
-                    if s.success() {
-                        let final_path = captured_path.lock().unwrap().clone();
-                        let _ = app_term.emit("download-finished", FinishPayload { id: id_term, code: s.code(), path: final_path });
-                    } else {
-                        let err_lines = error_lines.lock().unwrap();
-                        let out_lines = stdout_lines.lock().unwrap();
-                        
-                        let error_msg = if !err_lines.is_empty() {
-                            err_lines.join("\n")
-                        } else if !out_lines.is_empty() {
-                            format!("Process exited with code {:?}. Last output:\n{}", s.code(), out_lines.join("\n"))
-                        } else {
-                            format!("Process exited with code {:?} (No output captured)", s.code())
-                        };
-                        let _ = app_term.emit("download-error", ErrorPayload { id: id_term, error: error_msg });
-                    }
-                }
-                Err(e) => {
-                    let _ = app_term.emit("download-error", ErrorPayload { id: id_term, error: e.to_string() });
                 }
+                return;
             }
-        });
+        }
+
+        if succeeded {
+            app_term.state::<AppState>().partials.lock().unwrap().remove(&download_id);
+        }
+
+        let job = if succeeded {
+            let (final_path, files) = {
+                let tracker = tracker.lock().unwrap();
+                let final_path = if is_playlist { Some(target_dir.clone()) } else { tracker.final_path() };
+                (final_path, tracker.files())
+            };
+            let _ = app_term.emit("download-finished", FinishPayload { id: download_id.clone(), code: exit.code, path: final_path.clone(), files });
+            queue::finish(&app_term, &download_id, run, JobOutcome::Finished(final_path))
+        } else {
+            let error_msg = exit.error_message();
+            let _ = app_term.emit("download-error", ErrorPayload::new(download_id.clone(), error_msg.clone()));
+            queue::finish(&app_term, &download_id, run, JobOutcome::Failed(error_msg))
+        };
+
+        if let Some(job) = job {
+            record_history(&app_term, &job, exit.code, Some(&tracker.lock().unwrap()));
+        }
+    });
+
+    Ok(())
+}
+
+/// Writes the history entry for a job that has ended. `tracker` is missing for jobs
+/// whose process never ran (a failed launch, or a cancel while queued or paused).
+fn record_history(app: &AppHandle, job: &queue::QueueJob, exit_code: Option<i32>, tracker: Option<&OutputTracker>) {
+    let status = match job.status {
+        queue::JobStatus::Finished => HistoryStatus::Finished,
+        queue::JobStatus::Cancelled => HistoryStatus::Cancelled,
+        _ => HistoryStatus::Failed,
+    };
+    let mut meta = tracker.and_then(|t| t.meta()).unwrap_or_default();
+    if job.args.playlist {
+        // Per-video details describe only the last entry; the job is the playlist.
+        meta = output::MediaMeta { extractor_key: meta.extractor_key, ..Default::default() };
     }
+    let size = job.path.as_ref().and_then(|p| fs::metadata(p).ok()).map(|m| m.len());
+    let ext = job.path.as_ref()
+        .and_then(|p| std::path::Path::new(p).extension())
+        .map(|e| e.to_string_lossy().into_owned())
+        .or(meta.ext);
+
+    let entry = HistoryEntry {
+        id: job.id.clone(),
+        url: job.args.url.clone(),
+        extractor: meta.extractor_key,
+        video_id: meta.id,
+        title: meta.title.or_else(|| job.args.title.clone()),
+        download_type: job.args.download_type.clone(),
+        quality: job.args.quality.clone(),
+        ext,
+        output_dir: job.args.output_dir.clone(),
+        path: job.path.clone(),
+        files: tracker.map(|t| t.files()).unwrap_or_default(),
+        size,
+        duration: meta.duration,
+        started_at: job.started_at,
+        finished_at: job.finished_at.unwrap_or_else(|| chrono::Utc::now().timestamp()),
+        status,
+        exit_code,
+        error: job.error.clone(),
+        error_code: job.error_code,
+        args: job.args.clone(),
+        missing: false,
+    };
+    app.state::<AppState>().history.lock().unwrap().record(entry);
+    let _ = app.emit("history-updated", job.id.clone());
+}
 
-    #[cfg(not(windows))]
-    {
-        let shell = app.shell();
-        let (mut rx, child) = shell.command(ytdlp_path.to_str().unwrap())
-            .args(args)
-            .env("PATH", new_path)
-            .spawn()
-            .map_err(|e: tauri_plugin_shell::Error| e.to_string())?;
+/// Routes a yt-dlp stdout line: progress-template lines become typed `download-progress`
+/// events, output-file reports go to the tracker, everything else is forwarded verbatim
+/// as `ytdlp-output`.
+fn forward_stdout_line(app: &AppHandle, id: &str, line: String, tracker: &Mutex<OutputTracker>) {
+    if let Some(progress) = progress::parse_line(id, &line) {
+        forward_progress(app, id, progress);
+        return;
+    }
+    let observed = tracker.lock().unwrap().observe(&line);
+    match observed {
+        output::Observed::Other => {}
+        output::Observed::Recorded => {
+            println!("[YT-DLP OUTPUT] {}", line.trim());
+            return;
+        }
+        output::Observed::EntryFile { index, path } => {
+            println!("[YT-DLP OUTPUT] {}", line.trim());
+            let _ = app.emit("playlist-entry-finished", playlist::EntryFinished { id: id.to_string(), index, path });
+            return;
+        }
+    }
+    println!("[YT-DLP STDOUT] {}", line);
+    let _ = app.emit("ytdlp-output", ProgressPayload { id: id.to_string(), data: line });
+}
 
-        state.children.lock().unwrap().insert(download_id.clone(), ChildProcess::Tauri(child));
+/// Remembers the temporary file a progress line names and emits it as `download-progress`.
+fn forward_progress(app: &AppHandle, id: &str, progress: progress::DownloadProgress) {
+    if let Some(tmp) = &progress.tmpfilename {
+        let state = app.state::<AppState>();
+        let mut partials = state.partials.lock().unwrap();
+        let files = partials.entry(id.to_string()).or_default();
+        if !files.contains(tmp) {
+            files.push(tmp.clone());
+        }
+    }
+    let _ = app.emit("download-progress", progress);
+}
 
-        tauri::async_runtime::spawn(async move {
-            let mut last_error_lines = Vec::new();
-            while let Some(event) = rx.recv().await {
-                match event {
-                    CommandEvent::Stdout(line) => {
-                        let data = String::from_utf8_lossy(&line).into_owned();
-                        let _ = app_clone.emit("ytdlp-output", ProgressPayload { id: download_id.clone(), data });
-                    }
-                    CommandEvent::Stderr(line) => {
-                        let data = String::from_utf8_lossy(&line).into_owned();
-                        if !data.trim().is_empty() {
-                            last_error_lines.push(data.clone());
-                            if last_error_lines.len() > 10 { last_error_lines.remove(0); }
-                        }
-                        let formatted = format!("[STDERR] {}", data);
-                        let _ = app_clone.emit("ytdlp-output", ProgressPayload { id: download_id.clone(), data: formatted });
-                    }
-                    CommandEvent::Terminated(p) => {
-                        if p.code.unwrap_or(1) != 0 {
-                            let error_msg = if last_error_lines.is_empty() {
-                                format!("Process exited with code {:?}", p.code)
-                            } else {
-                                last_error_lines.join("\n")
-                            };
-                            let _ = app_clone.emit("download-error", ErrorPayload { id: download_id.clone(), error: error_msg });
-                        } else {
-                            let _ = app_clone.emit("download-finished", FinishPayload { id: download_id.clone(), code: p.code, path: None });
-                        }
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-        });
+/// Turns an `ERROR:` line of a playlist job into a `playlist-entry-error` event. The
+/// entry index is only known when the error concerns the video extracted last.
+fn report_entry_error(app: &AppHandle, id: &str, line: &str, tracker: &Mutex<OutputTracker>) {
+    let Some((video_id, error)) = playlist::parse_error_line(line) else {
+        return;
+    };
+    let index = tracker.lock().unwrap().meta()
+        .filter(|meta| meta.id.is_some() && meta.id == video_id)
+        .and_then(|meta| meta.playlist_index);
+    let _ = app.emit("playlist-entry-error", playlist::EntryError { id: id.to_string(), index, video_id, code: errors::classify(&error), error });
+}
+
+#[derive(Serialize)]
+struct CancelReport {
+    id: String,
+    /// The process ignored the interrupt and its tree was killed.
+    forced: bool,
+    /// Partial files still on disk, for `cleanup_partial_files` to delete.
+    leftover_files: Vec<String>,
+}
+
+/// Interrupts the job's process so it can finish writing, escalating to killing its
+/// whole tree, and resolves once it has exited.
+#[tauri::command]
+async fn cancel_download(app: AppHandle, payload: String, state: tauri::State<'_, AppState>) -> Result<CancelReport, String> {
+    let mut report = CancelReport { id: payload.clone(), forced: false, leftover_files: Vec::new() };
+    let previous = state.queue.lock().unwrap().cancel(&payload);
+    if matches!(previous, Some(queue::JobStatus::Pending | queue::JobStatus::Paused)) {
+        state.journal.lock().unwrap().remove(&payload);
+        state.partials.lock().unwrap().remove(&payload);
+        let job = state.queue.lock().unwrap().job(&payload);
+        if let Some(job) = job {
+            record_history(&app, &job, None, None);
+        }
+        let _ = app.emit("download-cancelled", payload);
+        queue::emit_state(&app);
+        return Ok(report);
+    }
+
+    let child = state.children.lock().unwrap().get(&payload).map(|c| c.pid);
+    if child.is_none() && previous == Some(queue::JobStatus::Active) {
+        // Still being spawned (`launch_download` kills the process once it sees the
+        // cancel) or waiting for an automatic retry: nothing to terminate.
+        state.partials.lock().unwrap().remove(&payload);
+        let _ = app.emit("download-cancelled", payload.clone());
+    }
+    if let Some(pid) = child {
+        report.forced = supervisor::terminate(&app, &payload, pid).await;
+        let tracked = state.partials.lock().unwrap().remove(&payload).unwrap_or_default();
+        report.leftover_files = tracked
+            .iter()
+            .flat_map(|tmp| output::leftover_files(tmp))
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        let msg = format!(
+            "Cancelled {}{}, {} partial file(s) left",
+            payload,
+            if report.forced { " (killed)" } else { "" },
+            report.leftover_files.len()
+        );
+        println!("[Queue] {}", msg);
+        let _ = app.emit("debug-log", msg);
+        let _ = app.emit("download-cancelled", payload);
     }
+    queue::pump(&app);
+    Ok(report)
+}
 
+/// Stops a download but keeps its partial files; `resume_download` continues it.
+#[tauri::command]
+fn pause_download(app: AppHandle, payload: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let previous = state.queue.lock().unwrap().pause(&payload)?;
+    let child = state.children.lock().unwrap().get(&payload).map(|c| c.pid);
+    match child.filter(|_| previous == queue::JobStatus::Active) {
+        // `download-paused` follows once the process has exited.
+        Some(pid) => {
+            let app = app.clone();
+            let id = payload.clone();
+            tauri::async_runtime::spawn(async move {
+                supervisor::terminate(&app, &id, pid).await;
+            });
+        }
+        None => {
+            let _ = app.emit("download-paused", payload);
+        }
+    }
+    queue::pump(&app);
     Ok(())
 }
 
 #[tauri::command]
-async fn cancel_download(app: AppHandle, payload: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut children = state.children.lock().unwrap();
-    if let Some(child_process) = children.remove(&payload) {
-        match child_process {
-            ChildProcess::Tauri(child) => {
-                let _pid = child.pid();
-                #[cfg(windows)]
-                {
-                    let mut cmd = std::process::Command::new("taskkill");
-                    cmd.args(["/F", "/T", "/PID", &_pid.to_string()]);
-                    #[cfg(windows)]
-                    cmd.creation_flags(CREATE_NO_WINDOW);
-                    let _ = cmd.status(); 
-                }
-                #[cfg(not(windows))]
-                {
-                    let _ = child.kill();
-                }
-            },
-            #[cfg(windows)]
-            ChildProcess::WindowsPid(pid) => {
-                let mut cmd = std::process::Command::new("taskkill");
-                cmd.args(["/F", "/T", "/PID", &pid.to_string()]);
-                #[cfg(windows)]
-                cmd.creation_flags(CREATE_NO_WINDOW);
-                let _ = cmd.status(); 
+fn resume_download(app: AppHandle, payload: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.queue.lock().unwrap().resume(&payload)?;
+    let _ = app.emit("download-resumed", payload);
+    queue::pump(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_queue(state: tauri::State<'_, AppState>) -> queue::QueueSnapshot {
+    let (max_concurrent, window) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.max_concurrent, schedule::current(&settings.schedule))
+    };
+    state.queue.lock().unwrap().snapshot(max_concurrent, window)
+}
+
+#[tauri::command]
+fn remove_queue_job(app: AppHandle, payload: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.queue.lock().unwrap().remove(&payload)?;
+    queue::emit_state(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn clear_finished_jobs(app: AppHandle, state: tauri::State<'_, AppState>) {
+    state.queue.lock().unwrap().clear_finished();
+    queue::emit_state(&app);
+}
+
+#[derive(Serialize)]
+struct InterruptedDownload {
+    id: String,
+    args: DownloadArgs,
+    output_template: Option<String>,
+    started_at: Option<i64>,
+}
+
+/// Jobs that were still running when the app last exited, for the UI to offer a resume.
+#[tauri::command]
+fn get_interrupted_downloads(state: tauri::State<'_, AppState>) -> Vec<InterruptedDownload> {
+    let jobs = state.queue.lock().unwrap().interrupted();
+    let journal = state.journal.lock().unwrap();
+    jobs.into_iter()
+        .map(|job| {
+            let entry = journal.get(&job.id);
+            InterruptedDownload {
+                id: job.id.clone(),
+                args: entry.map(|e| e.args.clone()).unwrap_or(job.args),
+                output_template: entry.map(|e| e.output_template.clone()),
+                started_at: entry.map(|e| e.started_at).or(job.started_at),
             }
+        })
+        .collect()
+}
+
+/// Re-queues interrupted jobs with `--continue` against their original output template.
+#[tauri::command]
+fn resume_interrupted_downloads(app: AppHandle, payload: Vec<String>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    for id in payload {
+        let args = state.journal.lock().unwrap().get(&id).map(|e| e.args.clone())
+            .or_else(|| state.queue.lock().unwrap().job_args(&id));
+        if let Some(mut args) = args {
+            args.resume = true;
+            state.queue.lock().unwrap().enqueue(args)?;
         }
-        std::thread::sleep(std::time::Duration::from_millis(200));
-        let _ = app.emit("download-cancelled", payload);
     }
+    queue::pump(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn discard_interrupted_downloads(app: AppHandle, payload: Vec<String>, state: tauri::State<'_, AppState>) {
+    for id in payload {
+        state.journal.lock().unwrap().remove(&id);
+        state.queue.lock().unwrap().cancel(&id);
+    }
+    queue::emit_state(&app);
+}
+
+#[derive(Deserialize)]
+struct DuplicateCheckPayload {
+    url: String,
+    extractor: Option<String>,
+    #[serde(rename = "videoId")]
+    video_id: Option<String>,
+    #[serde(rename = "outputDir")]
+    output_dir: String,
+}
+
+#[derive(Serialize)]
+struct DuplicateCheck {
+    duplicate: bool,
+    /// `"history"` or `"archive"`.
+    source: Option<String>,
+    path: Option<String>,
+    message: Option<String>,
+}
+
+/// Looks a video up in the history and the download archive before it is queued,
+/// so the UI can offer to skip or overwrite.
+#[tauri::command]
+fn check_duplicate(app: AppHandle, payload: DuplicateCheckPayload, state: tauri::State<'_, AppState>) -> DuplicateCheck {
+    let known = state.history.lock().unwrap()
+        .find_downloaded(&payload.url, payload.extractor.as_deref(), payload.video_id.as_deref());
+    if let Some(entry) = known {
+        let path = entry.path.unwrap_or_default();
+        return DuplicateCheck {
+            duplicate: true,
+            source: Some("history".into()),
+            message: Some(format!("Already downloaded at {}", path)),
+            path: Some(path),
+        };
+    }
+
+    let archive_mode = state.settings.lock().unwrap().archive_mode;
+    let archive = archive::archive_file(archive_mode, &app_state_dir(&app), &payload.output_dir);
+    if let (Some(archive), Some(extractor), Some(video_id)) = (archive, &payload.extractor, &payload.video_id) {
+        if archive::contains(&archive, extractor, video_id) {
+            return DuplicateCheck {
+                duplicate: true,
+                source: Some("archive".into()),
+                path: None,
+                message: Some(format!("Already recorded in the download archive {:?}", archive)),
+            };
+        }
+    }
+
+    DuplicateCheck { duplicate: false, source: None, path: None, message: None }
+}
+
+#[tauri::command]
+fn get_history(payload: Option<HistoryQuery>, state: tauri::State<'_, AppState>) -> Vec<HistoryEntry> {
+    state.history.lock().unwrap().search(&payload.unwrap_or_default())
+}
+
+#[derive(Deserialize)]
+struct ExportHistoryPayload {
+    path: String,
+    format: history::ExportFormat,
+    #[serde(default)]
+    query: HistoryQuery,
+}
+
+#[tauri::command]
+fn export_history(payload: ExportHistoryPayload, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let entries = state.history.lock().unwrap().search(&payload.query);
+    history::export(&entries, std::path::Path::new(&payload.path), payload.format)
+}
+
+/// Queues a fresh download with the same options as a history entry and returns its job id.
+#[tauri::command]
+fn requeue_history_entry(app: AppHandle, payload: String, state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let entry = state.history.lock().unwrap().get(&payload).ok_or("History entry not found")?;
+    let mut args = entry.args;
+    args.id = chrono::Utc::now().timestamp_millis().to_string();
+    args.resume = false;
+    let id = args.id.clone();
+    state.queue.lock().unwrap().enqueue(args)?;
+    queue::pump(&app);
+    Ok(id)
+}
+
+#[tauri::command]
+fn delete_history_entry(payload: String, state: tauri::State<'_, AppState>) {
+    state.history.lock().unwrap().remove(&payload);
+}
+
+#[tauri::command]
+fn clear_history(state: tauri::State<'_, AppState>) {
+    state.history.lock().unwrap().clear();
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<'_, AppState>) -> Settings {
+    state.settings.lock().unwrap().clone()
+}
+
+/// Built-in presets followed by the user's own.
+#[tauri::command]
+fn get_encoding_presets(state: tauri::State<'_, AppState>) -> Vec<presets::EncodingPreset> {
+    let mut all = presets::built_in();
+    all.extend(state.settings.lock().unwrap().custom_presets.clone());
+    all
+}
+
+#[tauri::command]
+fn update_settings(app: AppHandle, payload: Settings, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    payload.validate()?;
+    payload.save(&app_state_dir(&app).join("settings.json"))?;
+    *state.settings.lock().unwrap() = payload;
+    // A higher limit may free up slots for pending jobs.
+    queue::pump(&app);
     Ok(())
 }
 
@@ -852,19 +1373,37 @@ async fn open_path(payload: String) -> Result<(), String> {
 struct CleanupPayload {
     path: String,
     video_id: String,
+    /// Download id; its tracked temporary files are used when known.
+    id: Option<String>,
 }
 
 #[tauri::command]
-async fn cleanup_partial_files(payload: CleanupPayload) -> Result<(), String> {
+async fn cleanup_partial_files(payload: CleanupPayload, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let tracked = payload.id.as_ref().and_then(|id| state.partials.lock().unwrap().remove(id));
+    if let Some(tracked) = tracked {
+        println!("Cleaning up partial files for download {:?}", payload.id);
+        for tmp in tracked {
+            for file in output::leftover_files(&tmp) {
+                println!("Deleting partial file: {:?}", file);
+                let _ = std::fs::remove_file(file);
+            }
+        }
+        return Ok(());
+    }
+
+    // Nothing was tracked (e.g. the process died before reporting progress): fall back
+    // to matching the video id anywhere in the name.
     let download_path = std::path::Path::new(&payload.path);
     println!("Cleaning up partial files for ID: {} in path: {:?}", payload.video_id, download_path);
+    if payload.video_id.is_empty() {
+        return Ok(());
+    }
     
     if let Ok(entries) = std::fs::read_dir(download_path) {
         for entry in entries.flatten() {
             let file_name = entry.file_name().to_string_lossy().to_string();
-            let id_with_brackets = format!("[{}]", payload.video_id);
             
-            if (file_name.contains(&id_with_brackets) || file_name.contains(&payload.video_id)) && 
+            if file_name.contains(&payload.video_id) && 
                (file_name.ends_with(".part") || file_name.ends_with(".ytdl") || file_name.contains(".temp") || file_name.contains(".f")) {
                 println!("Deleting partial file: {}", file_name);
                 let _ = std::fs::remove_file(entry.path());
@@ -875,16 +1414,42 @@ async fn cleanup_partial_files(payload: CleanupPayload) -> Result<(), String> {
     Ok(())
 }
 
+#[derive(Deserialize)]
+struct PreviewPayload {
+    /// JSON returned by `get_video_info`.
+    info: serde_json::Value,
+    #[serde(rename = "outputDir")]
+    output_dir: String,
+    /// Template to preview; defaults to the one in settings.
+    template: Option<String>,
+    /// Extension the chosen format will end up with (e.g. after recoding).
+    ext: Option<String>,
+}
+
+/// Renders an output template against a video's metadata so the UI can show where
+/// the file will be saved.
+#[tauri::command]
+fn preview_output_path(payload: PreviewPayload, state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let template = payload.template.unwrap_or_else(|| state.settings.lock().unwrap().output_template.clone());
+    template::validate(&template)?;
+
+    let mut info = payload.info;
+    if let (Some(ext), Some(fields)) = (payload.ext, info.as_object_mut()) {
+        fields.insert("ext".into(), serde_json::Value::String(ext));
+    }
+    let relative = template::render(&template, &info)?;
+    Ok(std::path::Path::new(&payload.output_dir).join(relative).to_string_lossy().into_owned())
+}
+
 #[tauri::command]
-async fn download_dependencies(app: AppHandle) -> Result<(), String> {
+async fn download_dependencies(app: AppHandle) -> Result<(), checksums::DependencyError> {
     let local_app_data = app.path().local_data_dir().unwrap();
     let ytdlp_dir = local_app_data.join("YTDLP");
     let ffmpeg_dir = local_app_data.join("FFMPEG");
     let deno_dir = local_app_data.join("DENO");
 
     fs::create_dir_all(&ytdlp_dir).map_err(|e| e.to_string())?;
-    fs::create_dir_all(&ffmpeg_dir).map_err(|e| e.to_string())?;
-    fs::create_dir_all(&deno_dir).map_err(|e| e.to_string())?;
+    let transfer = app.state::<AppState>().settings.lock().unwrap().transfer.options();
 
     let _ = app.emit("dependencies-download-start", "Checking for system updates...");
 
@@ -905,71 +1470,145 @@ async fn download_dependencies(app: AppHandle) -> Result<(), String> {
     };
 
     if need_ytdlp {
-        download_file(&app, ytdlp_url, &ytdlp_path, "Updating Core Engine (yt-dlp)...").await?;
-        set_executable_permission(&ytdlp_path);
+        let temp = checksums::temp_path(&ytdlp_path);
+        download_verified(&app, ytdlp_url, YTDLP_SUMS_URL, &temp, "Updating Core Engine (yt-dlp)...", &transfer).await?;
+        set_executable_permission(&temp);
+        if let Err(e) = fs::rename(&temp, &ytdlp_path) {
+            let _ = fs::remove_file(&temp);
+            return Err(checksums::DependencyError::InstallFailed {
+                file: ytdlp_name.to_string(),
+                message: format!("Failed to install yt-dlp (is it still running?): {}", e),
+            });
+        }
     }
 
     let ffmpeg_name = if cfg!(windows) { "bin/ffmpeg.exe" } else { "bin/ffmpeg" };
     let ffmpeg_bin = ffmpeg_dir.join(ffmpeg_name);
     if !ffmpeg_bin.exists() {
-        let ffmpeg_zip = ffmpeg_dir.join("ffmpeg.zip");
         let ffmpeg_url = if cfg!(windows) {
             "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-win64-gpl-shared.zip"
         } else {
             "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-master-latest-linux64-gpl.tar.xz"
         };
+        let archive = checksums::temp_path(&local_app_data.join("ffmpeg-archive"));
+        download_verified(&app, ffmpeg_url, FFMPEG_SUMS_URL, &archive, "Downloading FFmpeg...", &transfer).await?;
 
-        if ffmpeg_url.ends_with(".zip") {
-            download_file(&app, ffmpeg_url, &ffmpeg_zip, "Downloading FFmpeg...").await?;
-            extract_zip(&ffmpeg_zip, &ffmpeg_dir)?;
+        let staging = staging_dir(&ffmpeg_dir)?;
+        let extracted = if ffmpeg_url.ends_with(".zip") {
+            extract_zip(&archive, &staging)
         } else {
-            download_file(&app, ffmpeg_url, &ffmpeg_zip, "Downloading FFmpeg...").await?;
-            extract_tar_xz(&ffmpeg_zip, &ffmpeg_dir)?;
-        }
-        let _ = fs::remove_file(ffmpeg_zip);
-        set_executable_permission(&ffmpeg_bin);
+            extract_tar_xz(&archive, &staging)
+        };
+        let _ = fs::remove_file(&archive);
+        extracted?;
+        install_dir(&staging, &ffmpeg_dir, ffmpeg_name)?;
+        start_encoder_probe(&app);
     }
 
     let deno_name = if cfg!(windows) { "deno.exe" } else { "deno" };
     let deno_bin = deno_dir.join(deno_name);
     if !deno_bin.exists() {
-        let deno_zip = deno_dir.join("deno.zip");
         let deno_url = if cfg!(windows) {
             "https://github.com/denoland/deno/releases/latest/download/deno-x86_64-pc-windows-msvc.zip"
         } else {
             "https://github.com/denoland/deno/releases/latest/download/deno-x86_64-unknown-linux-gnu.zip"
         };
-        download_file(&app, deno_url, &deno_zip, "Downloading Deno...").await?;
-        extract_zip(&deno_zip, &deno_dir)?;
-        let _ = fs::remove_file(deno_zip);
-        set_executable_permission(&deno_bin);
+        let archive = checksums::temp_path(&local_app_data.join("deno-archive"));
+        // Deno publishes one `<asset>.sha256sum` file per release asset.
+        let sums_url = format!("{}.sha256sum", deno_url);
+        download_verified(&app, deno_url, &sums_url, &archive, "Downloading Deno...", &transfer).await?;
+
+        let staging = staging_dir(&deno_dir)?;
+        let extracted = extract_zip(&archive, &staging);
+        let _ = fs::remove_file(&archive);
+        extracted?;
+        install_dir(&staging, &deno_dir, deno_name)?;
     }
 
     let _ = app.emit("dependencies-download-finished", ());
     Ok(())
 }
 
-async fn download_file(app: &AppHandle, url: &str, path: &PathBuf, msg: &str) -> Result<(), String> {
+/// Downloads `url` to `dest` and checks it against the SHA-256 its publisher lists in
+/// `sums_url`. A failed download is kept for the next try to resume; a file that fails
+/// verification is deleted.
+async fn download_verified(
+    app: &AppHandle,
+    url: &str,
+    sums_url: &str,
+    dest: &std::path::Path,
+    msg: &str,
+    options: &transfer::TransferOptions,
+) -> Result<(), checksums::DependencyError> {
+    let file_name = url.rsplit('/').next().unwrap_or(url);
+    let expected = checksums::published_hash(sums_url, file_name).await?;
+    download_file(app, url, dest, msg, options).await?;
+    checksums::verify(dest, file_name, &expected)?;
+    let _ = app.emit("debug-log", format!("Verified {} (sha256 {})", file_name, expected));
+    Ok(())
+}
+
+/// Empty folder next to `dest` to unpack an archive into before it is swapped in.
+fn staging_dir(dest: &std::path::Path) -> Result<PathBuf, String> {
+    let staging = checksums::temp_path(dest);
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+    Ok(staging)
+}
+
+/// Replaces `dest` with the unpacked `staging` folder, after checking that it contains
+/// `binary`. The old folder is only moved aside until the new one is in place, and put
+/// back if that fails, so `dest` is never left missing or half-written.
+fn install_dir(staging: &PathBuf, dest: &PathBuf, binary: &str) -> Result<(), checksums::DependencyError> {
+    let staged_bin = staging.join(binary);
+    if !staged_bin.exists() {
+        let _ = fs::remove_dir_all(staging);
+        return Err(format!("The downloaded archive does not contain {}", binary).into());
+    }
+    set_executable_permission(&staged_bin);
+    let failed = |e: std::io::Error| {
+        let _ = fs::remove_dir_all(staging);
+        checksums::DependencyError::InstallFailed {
+            file: binary.to_string(),
+            message: format!("Failed to install into {:?}: {}", dest, e),
+        }
+    };
+
+    let old = dest.with_extension("old");
+    // Left over from an install that was interrupted after the swap.
+    let _ = fs::remove_dir_all(&old);
+    let had_old = dest.exists();
+    if had_old {
+        fs::rename(dest, &old).map_err(failed)?;
+    }
+    if let Err(e) = fs::rename(staging, dest) {
+        if had_old {
+            let _ = fs::rename(&old, dest);
+        }
+        return Err(failed(e));
+    }
+    if had_old {
+        let _ = fs::remove_dir_all(&old);
+    }
+    Ok(())
+}
+
+/// Downloads `url` to `path`, resuming and retrying as described in `transfer::download`.
+async fn download_file(app: &AppHandle, url: &str, path: &std::path::Path, msg: &str, options: &transfer::TransferOptions) -> Result<(), String> {
     let client = reqwest::Client::builder()
         .user_agent(APP_USER_AGENT)
+        .connect_timeout(options.response_timeout)
         .build()
         .map_err(|e| e.to_string())?;
-    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
-    let total_size = response.content_length().unwrap_or(0);
-    let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-    let mut stream = response.bytes_stream();
-    let mut downloaded: u64 = 0;
-
-    use futures_util::StreamExt;
-    while let Some(item) = stream.next().await {
-        let chunk = item.map_err(|e| e.to_string())?;
-        std::io::copy(&mut &*chunk, &mut file).map_err(|e| e.to_string())?;
-        downloaded += chunk.len() as u64;
-        if total_size > 0 {
-            let percent = (downloaded as f64 / total_size as f64) * 100.0;
+    let size = transfer::download(&client, url, path, options, |progress| {
+        if let Some(percent) = progress.percent() {
             let _ = app.emit("dependencies-download-progress", serde_json::json!({ "percent": percent, "details": msg }));
         }
-    }
+    })
+    .await?;
+    println!("Downloaded {} ({} bytes)", url, size);
     Ok(())
 }
 
@@ -1238,7 +1877,8 @@ async fn download_and_install_update(app: AppHandle, payload: String) -> Result<
 
     println!("[Updater] Downloading update to: {:?}", dest_path);
 
-    download_file(&app, &url, &dest_path, "Downloading App Update...").await?;
+    let transfer = app.state::<AppState>().settings.lock().unwrap().transfer.options();
+    download_file(&app, &url, &dest_path, "Downloading App Update...", &transfer).await?;
 
     println!("[Updater] Download complete. Launching installer...");
 
@@ -1322,7 +1962,28 @@ pub fn run() {
     tauri::Builder::default()
         .manage(AppState { 
             children: Mutex::new(HashMap::new()),
-            hw_encoder: Mutex::new(None),
+            encoders: Mutex::new(None),
+            encoder_probe: tokio::sync::Mutex::new(()),
+            queue: Mutex::new(DownloadQueue::default()),
+            journal: Mutex::new(Journal::default()),
+            history: Mutex::new(History::default()),
+            settings: Mutex::new(Settings::default()),
+            partials: Mutex::new(HashMap::new()),
+            info_cache: Mutex::new(info_cache::InfoCache::default()),
+        })
+        .setup(|app| {
+            let handle = app.handle().clone();
+            let state_dir = app_state_dir(&handle);
+            let state = handle.state::<AppState>();
+            *state.settings.lock().unwrap() = Settings::load(&state_dir.join("settings.json"));
+            *state.queue.lock().unwrap() = DownloadQueue::load(&state_dir.join("queue.json"));
+            *state.journal.lock().unwrap() = Journal::load(&state_dir.join("journal.json"));
+            *state.history.lock().unwrap() = History::load(&state_dir.join("history.json"));
+            // Pick up jobs that were still pending when the app was last closed.
+            queue::pump(&handle);
+            schedule::start_ticker(&handle);
+            start_encoder_probe(&handle);
+            Ok(())
         })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
@@ -1333,7 +1994,12 @@ pub fn run() {
             select_folder, start_download, cancel_download, open_path, cleanup_partial_files,
             download_dependencies, update_ytdlp, restart_app,
             get_cookies_status, clear_cookies, extract_cookies, login_with_browser, system_action,
-            check_for_updates, download_and_install_update, get_app_version, delete_file
+            check_for_updates, download_and_install_update, get_app_version, delete_file,
+            get_queue, remove_queue_job, clear_finished_jobs, get_settings, update_settings,
+            get_interrupted_downloads, resume_interrupted_downloads, discard_interrupted_downloads,
+            get_history, export_history, requeue_history_entry, delete_history_entry, clear_history,
+            check_duplicate, preview_output_path, get_encoders,
+            get_encoding_presets, get_formats, pause_download, resume_download
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+/// Fragments fetched in parallel for HLS/DASH downloads unless configured otherwise.
+pub const DEFAULT_CONCURRENT_FRAGMENTS: u32 = 5;
+
+/// Bandwidth settings passed to yt-dlp. Rates are given the way yt-dlp takes them:
+/// bytes per second with an optional `K`, `M` or `G` suffix (e.g. `500K`, `2.5M`).
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransferLimits {
+    /// `--limit-rate`.
+    pub rate_limit: Option<String>,
+    /// `--throttled-rate`: below this speed yt-dlp assumes it is being throttled and
+    /// re-extracts the video.
+    pub throttled_rate: Option<String>,
+    /// `--concurrent-fragments`.
+    pub concurrent_fragments: Option<u32>,
+}
+
+/// Parses a yt-dlp rate into bytes per second.
+pub fn parse_rate(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, multiplier) = match text.chars().last()?.to_ascii_uppercase() {
+        'K' => (&text[..text.len() - 1], 1024.0),
+        'M' => (&text[..text.len() - 1], 1024.0 * 1024.0),
+        'G' => (&text[..text.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (text, 1.0),
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    let value: f64 = number.parse().ok()?;
+    let bytes = (value * multiplier) as u64;
+    (bytes > 0).then_some(bytes)
+}
+
+fn validate_rate(label: &str, rate: &Option<String>) -> Result<(), String> {
+    match rate {
+        Some(rate) if parse_rate(rate).is_none() => Err(format!("Invalid {}: {:?} (use e.g. 500K or 2M)", label, rate)),
+        _ => Ok(()),
+    }
+}
+
+impl TransferLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_rate("rate limit", &self.rate_limit)?;
+        validate_rate("throttled rate", &self.throttled_rate)?;
+        if self.concurrent_fragments.is_some_and(|n| n == 0 || n > 64) {
+            return Err("Concurrent fragments must be between 1 and 64".into());
+        }
+        Ok(())
+    }
+}
+
+/// Limits for one yt-dlp process.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EffectiveLimits {
+    /// Bytes per second.
+    pub rate_limit: Option<u64>,
+    pub throttled_rate: Option<u64>,
+    pub concurrent_fragments: u32,
+}
+
+/// Combines the global limits, a job's own limits and a throttling schedule window.
+/// The global and window rates are totals for all downloads. A process keeps the rate
+/// it was started with, so each one is capped at `rate / max_concurrent`: the total
+/// stays within the limit however many run, at the cost of leaving part of it unused
+/// while fewer do. A job's own rate can only lower that cap.
+pub fn effective(
+    global: &TransferLimits,
+    job: Option<&TransferLimits>,
+    window_rate: Option<&str>,
+    max_concurrent: usize,
+) -> EffectiveLimits {
+    let share = |rate: Option<&str>| rate.and_then(parse_rate).map(|r| (r / max_concurrent.max(1) as u64).max(1));
+    let rate_limit = [
+        share(global.rate_limit.as_deref()),
+        share(window_rate),
+        job.and_then(|j| j.rate_limit.as_deref()).and_then(parse_rate),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+
+    let throttled_rate = job
+        .and_then(|j| j.throttled_rate.as_deref())
+        .or(global.throttled_rate.as_deref())
+        .and_then(parse_rate);
+    let concurrent_fragments = job
+        .and_then(|j| j.concurrent_fragments)
+        .or(global.concurrent_fragments)
+        .unwrap_or(DEFAULT_CONCURRENT_FRAGMENTS);
+
+    EffectiveLimits { rate_limit, throttled_rate, concurrent_fragments }
+}
+
+impl EffectiveLimits {
+    pub fn push_args(&self, args: &mut Vec<String>) {
+        args.extend(["--concurrent-fragments".into(), self.concurrent_fragments.to_string()]);
+        if let Some(rate) = self.rate_limit {
+            args.extend(["--limit-rate".into(), rate.to_string()]);
+        }
+        if let Some(rate) = self.throttled_rate {
+            args.extend(["--throttled-rate".into(), rate.to_string()]);
+        }
+    }
+}
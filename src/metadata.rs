@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// Containers yt-dlp's `EmbedThumbnail` post-processor can write cover art into.
+const THUMBNAIL_CONTAINERS: &[&str] = &["mp3", "mkv", "mka", "ogg", "opus", "flac", "m4a", "mp4", "m4v", "mov"];
+
+/// One `--replace-in-metadata FIELDS REGEX REPLACE` rule.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MetadataReplacement {
+    /// Comma-separated field names, e.g. `title,artist`.
+    pub fields: String,
+    /// Python regular expression, passed to yt-dlp as-is.
+    pub pattern: String,
+    pub replacement: String,
+}
+
+/// Values written into the file's tags in place of what the site reports.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MetadataOverrides {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Use the playlist title as album when `album` is not set.
+    pub album_from_playlist: bool,
+    /// Use the playlist index as track number.
+    pub track_from_playlist_index: bool,
+    pub replacements: Vec<MetadataReplacement>,
+}
+
+/// What gets embedded into finished files. Stored in settings as the default and
+/// optionally overridden per job.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EmbedOptions {
+    pub metadata: bool,
+    pub chapters: bool,
+    /// Cover art; skipped for containers that cannot hold it (e.g. webm, wav).
+    pub thumbnail: bool,
+    pub overrides: MetadataOverrides,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        EmbedOptions { metadata: true, chapters: true, thumbnail: false, overrides: MetadataOverrides::default() }
+    }
+}
+
+/// A constant value as a `--parse-metadata` source. Bare words would be read as field
+/// names, so an always-empty field is appended to force template mode.
+fn literal(value: &str) -> String {
+    format!("{}%(hmd_literal|)s", value.replace('%', "%%").replace(':', "\\:"))
+}
+
+impl EmbedOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.overrides.replacements {
+            if rule.fields.trim().is_empty() || rule.pattern.is_empty() {
+                return Err("Metadata replacements need both fields and a pattern".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the yt-dlp flags for these options; `ext` is the container the job produces.
+    pub fn push_args(&self, download_type: &str, ext: &str, args: &mut Vec<String>) {
+        if download_type == "thumbnail" {
+            return;
+        }
+        if self.metadata {
+            args.push("--embed-metadata".into());
+        }
+        if self.chapters {
+            args.push("--embed-chapters".into());
+        }
+        if self.thumbnail {
+            if THUMBNAIL_CONTAINERS.contains(&ext) {
+                args.push("--embed-thumbnail".into());
+            } else {
+                println!("[Metadata] Cover art cannot be embedded into .{} files, skipping", ext);
+            }
+        }
+
+        let overrides = &self.overrides;
+        if let Some(artist) = overrides.artist.as_deref().filter(|a| !a.trim().is_empty()) {
+            args.extend(["--parse-metadata".into(), format!("{}:(?P<meta_artist>.+)", literal(artist))]);
+        }
+        if let Some(album) = overrides.album.as_deref().filter(|a| !a.trim().is_empty()) {
+            args.extend(["--parse-metadata".into(), format!("{}:(?P<meta_album>.+)", literal(album))]);
+        } else if overrides.album_from_playlist {
+            args.extend(["--parse-metadata".into(), "%(playlist_title|)s:(?P<meta_album>.+)".into()]);
+        }
+        if overrides.track_from_playlist_index {
+            args.extend(["--parse-metadata".into(), "%(playlist_index|)s:(?P<meta_track>\\d+)".into()]);
+        }
+        for rule in &overrides.replacements {
+            args.extend([
+                "--replace-in-metadata".into(),
+                rule.fields.replace(' ', ""),
+                rule.pattern.clone(),
+                rule.replacement.clone(),
+            ]);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const META_MARKER: &str = "[hmd-meta]";
+const FILE_MARKER: &str = "[hmd-file]";
+const THUMBNAILS_MARKER: &str = "[hmd-thumbnails]";
+const SUBTITLES_MARKER: &str = "[hmd-subtitles]";
+
+/// `--print` arguments that make yt-dlp report every file it produced, independent of
+/// its human-readable log lines (which `--print` silences via `--quiet`).
+///
+/// Basic metadata is printed as soon as extraction is done, so it is known even if the
+/// download later fails. The main media path is printed once it has been moved into
+/// place; thumbnails and subtitles are printed after all formats of the video are
+/// processed, which also covers `--skip-download` runs where `after_move` never happens.
+pub fn print_args() -> Vec<String> {
+    vec![
+        "--print".into(),
+        format!("video:{} %(.{{id,extractor_key,title,duration,ext,playlist_index}})j", META_MARKER),
+        "--print".into(),
+        format!("after_move:{} %(playlist_index)s|%(filepath)s", FILE_MARKER),
+        "--print".into(),
+        format!("after_video:{} %(thumbnails.:.filepath)j", THUMBNAILS_MARKER),
+        "--print".into(),
+        format!("after_video:{} %(requested_subtitles)j", SUBTITLES_MARKER),
+    ]
+}
+
+/// Extraction metadata reported by the `video:` print.
+#[derive(Deserialize, Clone, Default)]
+pub struct MediaMeta {
+    pub id: Option<String>,
+    pub extractor_key: Option<String>,
+    pub title: Option<String>,
+    pub duration: Option<f64>,
+    pub ext: Option<String>,
+    pub playlist_index: Option<u64>,
+}
+
+/// What `OutputTracker::observe` made of a line.
+pub enum Observed {
+    /// Not one of our markers.
+    Other,
+    Recorded,
+    /// A playlist entry's media file was moved into place.
+    EntryFile { index: u64, path: String },
+}
+
+/// Collects the files reported by `print_args` for one yt-dlp process.
+#[derive(Default)]
+pub struct OutputTracker {
+    main: Vec<String>,
+    extras: Vec<String>,
+    meta: Option<MediaMeta>,
+}
+
+fn usable(path: &str) -> Option<String> {
+    let path = path.trim();
+    if path.is_empty() || path == "NA" {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+impl OutputTracker {
+    /// Consumes marker lines; returns `Observed::Other` for any other output.
+    pub fn observe(&mut self, line: &str) -> Observed {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix(META_MARKER) {
+            if let Ok(meta) = serde_json::from_str(rest.trim()) {
+                self.meta = Some(meta);
+            }
+        } else if let Some(rest) = line.strip_prefix(FILE_MARKER) {
+            // "<playlist index or NA>|<path>"; the path itself may contain '|'.
+            let (index, path) = match rest.trim_start().split_once('|') {
+                Some((index, path)) if index == "NA" || index.parse::<u64>().is_ok() => (index.parse().ok(), path),
+                _ => (None, rest),
+            };
+            if let Some(path) = usable(path) {
+                push_unique(&mut self.main, path.clone());
+                if let Some(index) = index {
+                    return Observed::EntryFile { index, path };
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix(THUMBNAILS_MARKER) {
+            if let Ok(paths) = serde_json::from_str::<Vec<serde_json::Value>>(rest.trim()) {
+                for path in paths.iter().filter_map(|p| p.as_str()).filter_map(usable) {
+                    push_unique(&mut self.extras, path);
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix(SUBTITLES_MARKER) {
+            if let Ok(serde_json::Value::Object(subs)) = serde_json::from_str(rest.trim()) {
+                for sub in subs.values() {
+                    if let Some(path) = sub.get("filepath").and_then(|p| p.as_str()).and_then(usable) {
+                        push_unique(&mut self.extras, path);
+                    }
+                }
+            }
+        } else {
+            return Observed::Other;
+        }
+        Observed::Recorded
+    }
+
+    /// True once at least one media file was produced (a playlist with some failed entries still counts).
+    pub fn produced_media(&self) -> bool {
+        !self.main.is_empty()
+    }
+
+    pub fn meta(&self) -> Option<MediaMeta> {
+        self.meta.clone()
+    }
+
+    /// The primary output: the media file, or the first thumbnail/subtitle for
+    /// thumbnail-only jobs.
+    pub fn final_path(&self) -> Option<String> {
+        self.main.last().cloned().or_else(|| self.files().into_iter().next())
+    }
+
+    /// Every reported file that still exists on disk (embedded thumbnails and
+    /// subtitles are deleted by yt-dlp after embedding).
+    pub fn files(&self) -> Vec<String> {
+        self.main
+            .iter()
+            .chain(self.extras.iter())
+            .filter(|p| Path::new(p).exists())
+            .cloned()
+            .collect()
+    }
+}
+
+/// The `f137` in `Title.f137.mp4`: yt-dlp's name for one unmerged stream.
+fn is_format_id(segment: &str) -> bool {
+    segment.strip_prefix('f').is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Whether `rest`, the part of a file name after the download's base name, marks a
+/// leftover: `.<ext>` or `.f<id>.<ext>` followed by `.part`, `.ytdl` or `.part-Frag<n>`,
+/// a finished but unmerged `.f<id>.<ext>` stream, or a post-processor `.temp.<ext>`.
+/// Other names sharing the base (`Title.final.mp4`, `Title.fr.srt`) are not ours.
+fn is_leftover(rest: &str) -> bool {
+    let Some(rest) = rest.strip_prefix('.') else {
+        return false;
+    };
+    let mut segments: Vec<&str> = rest.split('.').collect();
+    let mut temporary = false;
+    while let Some(last) = segments.last() {
+        if *last == "part" || *last == "ytdl" || last.starts_with("part-Frag") {
+            segments.pop();
+            temporary = true;
+        } else {
+            break;
+        }
+    }
+    match segments.as_slice() {
+        [format, _ext] => is_format_id(format) || *format == "temp",
+        [_ext] => temporary,
+        _ => false,
+    }
+}
+
+/// Leftovers of an interrupted download in the folder of `tmpfilename` (the file yt-dlp
+/// reported writing): `.part`/`.ytdl` files, fragments, unmerged `.f<format>` streams
+/// and post-processor `.temp` files that share its base name. The finished output
+/// itself is never included.
+pub fn leftover_files(tmpfilename: &str) -> Vec<PathBuf> {
+    let tmp = Path::new(tmpfilename);
+    let (Some(dir), Some(name)) = (tmp.parent(), tmp.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+    let name = name.strip_suffix(".part").unwrap_or(&name);
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    // "Title.f137.mp4" and the merged "Title.mp4" share the base "Title".
+    let base = match stem.rsplit_once('.') {
+        Some((base, format)) if is_format_id(format) => base,
+        _ => stem,
+    };
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            file_name.strip_prefix(base).is_some_and(is_leftover)
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+fn push_unique(list: &mut Vec<String>, path: String) {
+    if !list.contains(&path) {
+        list.push(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_only_the_leftovers_of_the_download() {
+        let dir = std::env::temp_dir().join(format!("hmd-leftovers-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let leftovers = [
+            "Title.f137.mp4.part",
+            "Title.f137.mp4.ytdl",
+            "Title.f137.mp4.part-Frag12",
+            "Title.f137.mp4.part-Frag12.part",
+            "Title.f251.webm",
+            "Title.temp.mp4",
+            "Title.mp4.part",
+        ];
+        let kept = ["Title.mp4", "Title.final.mp4", "Title.fr.srt", "Title.f137", "Title.webp", "Other.f137.mp4", "Title 2.f137.mp4"];
+        for name in leftovers.iter().chain(kept.iter()) {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let tmp = dir.join("Title.f137.mp4.part");
+        let mut found: Vec<String> = leftover_files(&tmp.to_string_lossy())
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        found.sort();
+        let mut expected: Vec<String> = leftovers.iter().map(|s| s.to_string()).collect();
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn matches_leftover_suffixes() {
+        for rest in [".f137.mp4", ".f137.mp4.part", ".mp4.ytdl", ".mp4.part-Frag3", ".temp.mkv"] {
+            assert!(is_leftover(rest), "{}", rest);
+        }
+        for rest in [".mp4", ".final.mp4", ".fr.srt", ".fr.srt.part", ".f.mp4", ".f137x.mp4", "", " 2.f137.mp4", ".a.b.mp4.part"] {
+            assert!(!is_leftover(rest), "{}", rest);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use serde::Serialize;
+
+use crate::errors::ErrorCode;
+
+/// An entry of a playlist job that yt-dlp finished moving into place, emitted as
+/// `playlist-entry-finished`.
+#[derive(Serialize, Clone)]
+pub struct EntryFinished {
+    pub id: String,
+    /// 1-based position in the full playlist (not in the selection).
+    pub index: u64,
+    pub path: String,
+}
+
+/// An entry that failed while the rest of the playlist carried on (`--ignore-errors`),
+/// emitted as `playlist-entry-error`.
+#[derive(Serialize, Clone)]
+pub struct EntryError {
+    pub id: String,
+    /// Known when the failing video had already been extracted.
+    pub index: Option<u64>,
+    pub video_id: Option<String>,
+    pub code: ErrorCode,
+    pub error: String,
+}
+
+/// Folder a playlist is written into, using the same replacements as the renderer's
+/// `sanitizeFilename` so both sides agree on the path.
+pub fn folder_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| if "<>:\"/\\|?*".contains(c) || c.is_control() { '_' } else { c })
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "Playlist".into()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Accepts yt-dlp `--playlist-items` syntax: indices, ranges and slices such as `1,3,5-7,10:20:2`.
+pub fn validate_items(items: &str) -> Result<(), String> {
+    let valid = !items.trim().is_empty()
+        && items.chars().all(|c| c.is_ascii_digit() || ",-: ".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid playlist selection: {:?}", items))
+    }
+}
+
+/// Splits an `ERROR: [extractor] <video id>: <message>` line into the video id (when
+/// present) and the message.
+pub fn parse_error_line(line: &str) -> Option<(Option<String>, String)> {
+    let rest = line.trim().strip_prefix("ERROR:")?.trim();
+    let video_id = rest
+        .strip_prefix('[')
+        .and_then(|r| r.split_once("] "))
+        .and_then(|(_, r)| r.split_once(": "))
+        .map(|(id, _)| id.trim().to_string())
+        .filter(|id| !id.is_empty() && !id.contains(' '));
+    Some((video_id, rest.to_string()))
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use crate::encoders::{EncoderFamily, EncoderInfo, VideoCodec};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Speed {
+    Fast,
+    #[default]
+    Balanced,
+    Slow,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Mp3,
+    /// Keep the source audio untouched.
+    Copy,
+}
+
+impl AudioCodec {
+    /// Codec name as understood by `recode::compatible`; unknown for a copy.
+    pub fn family(self) -> Option<&'static str> {
+        match self {
+            AudioCodec::Aac => Some("aac"),
+            AudioCodec::Opus => Some("opus"),
+            AudioCodec::Mp3 => Some("mp3"),
+            AudioCodec::Copy => None,
+        }
+    }
+}
+
+/// Named quality settings for transcodes, translated per encoder family.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodingPreset {
+    pub name: String,
+    /// Constant-quality target on the x264 CRF scale (0-51, lower is better); other
+    /// encoders get the nearest equivalent.
+    pub quality: u8,
+    #[serde(default)]
+    pub speed: Speed,
+    /// Caps peaks for constant-quality modes, in kbit/s.
+    pub max_bitrate: Option<u32>,
+    /// In kbit/s; ffmpeg's default when unset.
+    pub audio_bitrate: Option<u32>,
+    /// ffmpeg picks the container's default when unset.
+    pub audio_codec: Option<AudioCodec>,
+}
+
+pub const DEFAULT_PRESET: &str = "balanced";
+
+/// Presets that always exist; custom presets from settings are listed after them.
+pub fn built_in() -> Vec<EncodingPreset> {
+    let preset = |name: &str, quality, speed, audio_bitrate| EncodingPreset {
+        name: name.into(),
+        quality,
+        speed,
+        max_bitrate: None,
+        audio_bitrate: Some(audio_bitrate),
+        // Left to ffmpeg, so the audio always fits the target container.
+        audio_codec: None,
+    };
+    vec![
+        preset("high", 18, Speed::Slow, 256),
+        preset(DEFAULT_PRESET, 23, Speed::Balanced, 192),
+        preset("small", 28, Speed::Fast, 128),
+    ]
+}
+
+/// Looks `name` up among built-in and custom presets, falling back to the default one.
+pub fn find(custom: &[EncodingPreset], name: &str) -> EncodingPreset {
+    let all = built_in();
+    custom
+        .iter()
+        .chain(all.iter())
+        .find(|p| p.name == name)
+        .or_else(|| all.iter().find(|p| p.name == DEFAULT_PRESET))
+        .cloned()
+        .expect("default preset is built in")
+}
+
+impl EncodingPreset {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Encoding presets need a name".into());
+        }
+        if self.quality > 51 {
+            return Err(format!("Preset {:?}: quality must be between 0 and 51", self.name));
+        }
+        if self.max_bitrate == Some(0) || self.audio_bitrate == Some(0) {
+            return Err(format!("Preset {:?}: bitrates must be positive", self.name));
+        }
+        Ok(())
+    }
+
+    fn speed_name(&self, family: EncoderFamily) -> &'static str {
+        match (family, self.speed) {
+            (EncoderFamily::Nvenc, Speed::Fast) => "p2",
+            (EncoderFamily::Nvenc, Speed::Balanced) => "p4",
+            (EncoderFamily::Nvenc, Speed::Slow) => "p6",
+            (EncoderFamily::Amf, Speed::Fast) => "speed",
+            (EncoderFamily::Amf, Speed::Balanced) => "balanced",
+            (EncoderFamily::Amf, Speed::Slow) => "quality",
+            (_, Speed::Fast) => "veryfast",
+            (_, Speed::Balanced) => "medium",
+            (_, Speed::Slow) => "slow",
+        }
+    }
+
+    /// ffmpeg video arguments for `encoder`, added after its `-c:v`.
+    pub fn video_args(&self, encoder: &EncoderInfo) -> Vec<String> {
+        let q = self.quality.to_string();
+        let speed = self.speed_name(encoder.family).to_string();
+        let mut args: Vec<String> = match encoder.family {
+            EncoderFamily::Software if encoder.codec == VideoCodec::Av1 => {
+                // SVT-AV1 uses a 0-63 CRF scale and numeric presets (lower is slower).
+                let crf = (self.quality as u32 * 63 / 51).to_string();
+                let preset = match self.speed {
+                    Speed::Fast => "10",
+                    Speed::Balanced => "8",
+                    Speed::Slow => "5",
+                };
+                vec!["-crf".into(), crf, "-preset".into(), preset.into()]
+            }
+            EncoderFamily::Software => vec!["-crf".into(), q, "-preset".into(), speed],
+            EncoderFamily::Nvenc => vec!["-rc".into(), "vbr".into(), "-cq".into(), q, "-b:v".into(), "0".into(), "-preset".into(), speed],
+            EncoderFamily::Qsv => vec!["-global_quality".into(), q, "-preset".into(), speed],
+            EncoderFamily::Vaapi => vec!["-rc_mode".into(), "CQP".into(), "-qp".into(), q],
+            EncoderFamily::Amf => vec![
+                "-rc".into(), "cqp".into(), "-qp_i".into(), q.clone(), "-qp_p".into(), q, "-quality".into(), speed,
+            ],
+            // VideoToolbox takes 1-100 with higher meaning better.
+            EncoderFamily::VideoToolbox => {
+                let scaled = (100 - (self.quality as i32 * 2)).clamp(1, 100);
+                vec!["-q:v".into(), scaled.to_string()]
+            }
+            // V4L2 M2M encoders only understand bitrates.
+            EncoderFamily::V4l2m2m => Vec::new(),
+        };
+
+        if let Some(max) = self.max_bitrate {
+            if encoder.family == EncoderFamily::V4l2m2m {
+                args.extend(["-b:v".into(), format!("{}k", max)]);
+            } else {
+                args.extend(["-maxrate".into(), format!("{}k", max), "-bufsize".into(), format!("{}k", max * 2)]);
+            }
+        }
+        args
+    }
+
+    pub fn audio_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.audio_codec {
+            Some(AudioCodec::Copy) => return vec!["-c:a".into(), "copy".into()],
+            Some(AudioCodec::Aac) => args.extend(["-c:a".into(), "aac".into()]),
+            Some(AudioCodec::Opus) => args.extend(["-c:a".into(), "libopus".into()]),
+            Some(AudioCodec::Mp3) => args.extend(["-c:a".into(), "libmp3lame".into()]),
+            None => {}
+        }
+        if let Some(bitrate) = self.audio_bitrate {
+            args.extend(["-b:a".into(), format!("{}k", bitrate)]);
+        }
+        args
+    }
+}
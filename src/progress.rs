@@ -0,0 +1,190 @@
+use serde::Serialize;
+
+/// Marker that prefixes every line produced by our `--progress-template`s.
+const PROGRESS_MARKER: &str = "[hmd-progress]";
+
+/// `--progress-template` arguments that make yt-dlp print machine-readable,
+/// `|`-separated progress lines instead of its human-oriented progress bar.
+/// Unknown fields are printed as `NA` by yt-dlp. The temporary filename comes last
+/// because it may itself contain `|`.
+pub fn template_args() -> Vec<String> {
+    vec![
+        "--progress-template".into(),
+        format!(
+            "download:{} download|%(progress.status)s|%(progress.downloaded_bytes)s|%(progress.total_bytes)s|%(progress.total_bytes_estimate)s|%(progress.speed)s|%(progress.eta)s|%(progress.fragment_index)s|%(progress.fragment_count)s|%(info.playlist_index)s|%(info.n_entries)s|%(progress.tmpfilename)s",
+            PROGRESS_MARKER
+        ),
+        "--progress-template".into(),
+        format!(
+            "postprocess:{} postprocess|%(progress.status)s|%(progress.postprocessor)s",
+            PROGRESS_MARKER
+        ),
+    ]
+}
+
+/// Whether `line` was produced by one of our templates. yt-dlp writes them to stderr
+/// when `--print` is used, since that implies `--quiet`.
+pub fn is_progress_line(line: &str) -> bool {
+    line.trim_start().starts_with(PROGRESS_MARKER)
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Downloading,
+    Downloaded,
+    Merging,
+    Recoding,
+    Remuxing,
+    ExtractingAudio,
+    Embedding,
+    Postprocessing,
+}
+
+impl Phase {
+    fn from_postprocessor(name: &str) -> Phase {
+        if name.contains("Merger") {
+            Phase::Merging
+        } else if name.contains("VideoConvertor") {
+            Phase::Recoding
+        } else if name.contains("VideoRemuxer") {
+            Phase::Remuxing
+        } else if name.contains("ExtractAudio") {
+            Phase::ExtractingAudio
+        } else if name.contains("Embed") || name.contains("Metadata") {
+            Phase::Embedding
+        } else {
+            Phase::Postprocessing
+        }
+    }
+}
+
+/// Typed replacement for scraping `[download]  42.0% of ...` lines, emitted as `download-progress`.
+#[derive(Serialize, Clone, Debug)]
+pub struct DownloadProgress {
+    pub id: String,
+    pub phase: Phase,
+    pub percent: Option<f64>,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    /// True when `total_bytes` is yt-dlp's estimate (fragmented streams).
+    pub total_is_estimate: bool,
+    /// Bytes per second.
+    pub speed: Option<f64>,
+    /// Seconds remaining.
+    pub eta: Option<u64>,
+    pub fragment_index: Option<u64>,
+    pub fragment_count: Option<u64>,
+    pub postprocessor: Option<String>,
+    /// 1-based position in the full playlist for playlist jobs.
+    pub playlist_index: Option<u64>,
+    /// Number of entries selected for download in a playlist job.
+    pub playlist_count: Option<u64>,
+    /// File yt-dlp is currently writing (usually `<final name>.part`).
+    pub tmpfilename: Option<String>,
+}
+
+fn field<T: std::str::FromStr>(value: Option<&str>) -> Option<T> {
+    let value = value?.trim();
+    if value.is_empty() || value == "NA" || value == "None" {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// yt-dlp prints integer byte counts as floats for some extractors ("1024.0").
+fn int_field(value: Option<&str>) -> Option<u64> {
+    field::<f64>(value).filter(|v| *v >= 0.0).map(|v| v as u64)
+}
+
+/// Parses a line emitted by one of our progress templates; returns `None` for any other output.
+pub fn parse_line(id: &str, line: &str) -> Option<DownloadProgress> {
+    let rest = line.trim().strip_prefix(PROGRESS_MARKER)?.trim_start();
+    let mut parts = rest.splitn(12, '|');
+    let kind = parts.next()?;
+    let status = parts.next().unwrap_or("");
+
+    let mut progress = DownloadProgress {
+        id: id.to_string(),
+        phase: Phase::Downloading,
+        percent: None,
+        downloaded_bytes: None,
+        total_bytes: None,
+        total_is_estimate: false,
+        speed: None,
+        eta: None,
+        fragment_index: None,
+        fragment_count: None,
+        postprocessor: None,
+        playlist_index: None,
+        playlist_count: None,
+        tmpfilename: None,
+    };
+
+    match kind {
+        "download" => {
+            progress.downloaded_bytes = int_field(parts.next());
+            let total = int_field(parts.next());
+            let estimate = int_field(parts.next());
+            progress.total_is_estimate = total.is_none() && estimate.is_some();
+            progress.total_bytes = total.or(estimate);
+            progress.speed = field(parts.next());
+            progress.eta = int_field(parts.next());
+            progress.fragment_index = int_field(parts.next());
+            progress.fragment_count = int_field(parts.next());
+            progress.playlist_index = int_field(parts.next());
+            progress.playlist_count = int_field(parts.next());
+            progress.tmpfilename = parts.next().map(str::trim).filter(|f| !f.is_empty() && *f != "NA").map(String::from);
+
+            progress.percent = match (progress.downloaded_bytes, progress.total_bytes) {
+                (Some(done), Some(total)) if total > 0 => Some((done as f64 / total as f64 * 100.0).min(100.0)),
+                _ => match (progress.fragment_index, progress.fragment_count) {
+                    (Some(index), Some(count)) if count > 0 => Some((index as f64 / count as f64 * 100.0).min(100.0)),
+                    _ => None,
+                },
+            };
+
+            if status == "finished" {
+                progress.phase = Phase::Downloaded;
+                progress.percent = Some(100.0);
+            }
+        }
+        "postprocess" => {
+            let name = parts.next().unwrap_or("").trim().to_string();
+            progress.phase = Phase::from_postprocessor(&name);
+            progress.postprocessor = Some(name).filter(|n| !n.is_empty() && n != "NA");
+        }
+        _ => return None,
+    }
+
+    Some(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_postprocess_lines_from_stderr() {
+        for (line, phase) in [
+            ("[hmd-progress] postprocess|started|Merger", Phase::Merging),
+            ("[hmd-progress] postprocess|started|VideoConvertor", Phase::Recoding),
+            ("[hmd-progress] postprocess|processing|EmbedThumbnail", Phase::Embedding),
+        ] {
+            assert!(is_progress_line(line));
+            let progress = parse_line("job", line).unwrap();
+            assert_eq!(progress.phase, phase);
+        }
+        assert!(!is_progress_line("ERROR: Postprocessing: Conversion failed!"));
+        assert!(parse_line("job", "[Merger] Merging formats into \"a.mp4\"").is_none());
+    }
+
+    #[test]
+    fn parses_download_lines() {
+        let line = "[hmd-progress] download|downloading|500|1000|NA|250.5|2|NA|NA|NA|NA|a.f137.mp4.part";
+        let progress = parse_line("job", line).unwrap();
+        assert_eq!(progress.phase, Phase::Downloading);
+        assert_eq!(progress.percent, Some(50.0));
+        assert_eq!(progress.tmpfilename.as_deref(), Some("a.f137.mp4.part"));
+    }
+}
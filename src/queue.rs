@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::schedule::{self, ScheduleWindow, WindowAction};
+use crate::errors::{self, ErrorCode};
+use crate::{storage, AppState, DownloadArgs};
+
+/// How many finished/failed/cancelled jobs are kept on disk.
+const MAX_FINISHED_JOBS: usize = 200;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Active,
+    /// Was running when the app exited; waits for the user to resume or discard it.
+    Interrupted,
+    /// Stopped by the user with its partial files kept; waits for `resume`.
+    Paused,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_done(self) -> bool {
+        matches!(self, JobStatus::Finished | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct QueueJob {
+    pub id: String,
+    pub args: DownloadArgs,
+    pub status: JobStatus,
+    /// Bumped every time the job is started so a late exit from a previous
+    /// process (e.g. one killed for a pause) cannot complete the new run.
+    #[serde(default)]
+    pub run: u64,
+    pub added_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub path: Option<String>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+}
+
+/// Outcome reported by the process runner when a job's yt-dlp process exits.
+pub enum JobOutcome {
+    Finished(Option<String>),
+    Failed(String),
+}
+
+#[derive(Serialize, Clone)]
+pub struct QueueSnapshot {
+    pub jobs: Vec<QueueJob>,
+    pub max_concurrent: usize,
+    pub active: usize,
+    pub pending: usize,
+    pub paused: usize,
+    /// Schedule window currently pausing or throttling the queue.
+    pub window: Option<ScheduleWindow>,
+}
+
+#[derive(Default)]
+pub struct DownloadQueue {
+    jobs: Vec<QueueJob>,
+    file: Option<PathBuf>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl DownloadQueue {
+    /// Loads the persisted queue. Jobs that were running when the app went away
+    /// cannot be re-attached to a process, so they are parked as interrupted.
+    pub fn load(file: &Path) -> DownloadQueue {
+        let mut jobs: Vec<QueueJob> = storage::load_json(file);
+        for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Active) {
+            job.status = JobStatus::Interrupted;
+        }
+        let queue = DownloadQueue { jobs, file: Some(file.to_path_buf()) };
+        queue.save();
+        queue
+    }
+
+    fn save(&self) {
+        if let Some(file) = &self.file {
+            if let Err(e) = storage::save_json(file, &self.jobs) {
+                println!("[Queue] Failed to persist queue: {}", e);
+            }
+        }
+    }
+
+    /// Adds a job, or re-queues an existing one with the same id (e.g. after a resume).
+    pub fn enqueue(&mut self, args: DownloadArgs) -> Result<(), String> {
+        let job = QueueJob {
+            id: args.id.clone(),
+            args,
+            status: JobStatus::Pending,
+            run: 0,
+            added_at: now(),
+            started_at: None,
+            finished_at: None,
+            path: None,
+            error: None,
+            error_code: None,
+        };
+        match self.jobs.iter_mut().find(|j| j.id == job.id) {
+            Some(existing) if existing.status == JobStatus::Active => {
+                return Err(format!("Download {} is already running", job.id));
+            }
+            Some(existing) => {
+                let run = existing.run;
+                *existing = QueueJob { run, ..job };
+            }
+            None => self.jobs.push(job),
+        }
+        self.save();
+        Ok(())
+    }
+
+    /// Marks as many pending jobs active as the concurrency limit allows and returns them.
+    pub fn take_runnable(&mut self, max_concurrent: usize) -> Vec<QueueJob> {
+        let active = self.count(JobStatus::Active);
+        let slots = max_concurrent.saturating_sub(active);
+        let mut started = Vec::new();
+        for job in self.jobs.iter_mut().filter(|j| j.status == JobStatus::Pending).take(slots) {
+            job.status = JobStatus::Active;
+            job.started_at = Some(now());
+            job.run += 1;
+            started.push(job.clone());
+        }
+        if !started.is_empty() {
+            self.save();
+        }
+        started
+    }
+
+    /// Records the process outcome and returns the updated job, or `None` when the
+    /// exit belongs to an older run. Cancelled jobs keep their status because killing
+    /// the process also surfaces as a non-zero exit.
+    pub fn complete(&mut self, id: &str, run: u64, outcome: JobOutcome) -> Option<QueueJob> {
+        let job = self.jobs.iter_mut().find(|j| j.id == id && j.run == run)?;
+        if job.status == JobStatus::Active {
+            job.finished_at = Some(now());
+            match outcome {
+                JobOutcome::Finished(path) => {
+                    job.status = JobStatus::Finished;
+                    job.path = path;
+                }
+                JobOutcome::Failed(error) => {
+                    job.status = JobStatus::Failed;
+                    job.error_code = Some(errors::classify(&error));
+                    job.error = Some(error);
+                }
+            }
+        }
+        let job = job.clone();
+        self.prune();
+        self.save();
+        Some(job)
+    }
+
+    /// Returns the status the job had before it was cancelled, if it existed.
+    pub fn cancel(&mut self, id: &str) -> Option<JobStatus> {
+        let job = self.jobs.iter_mut().find(|j| j.id == id)?;
+        let previous = job.status;
+        if !previous.is_done() {
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(now());
+            self.save();
+        }
+        Some(previous)
+    }
+
+    /// Parks a pending or running job, returning its previous status. Like a cancel,
+    /// the process exit that follows leaves the status alone.
+    pub fn pause(&mut self, id: &str) -> Result<JobStatus, String> {
+        let job = self.jobs.iter_mut().find(|j| j.id == id).ok_or_else(|| format!("Unknown download {}", id))?;
+        let previous = job.status;
+        if !matches!(previous, JobStatus::Pending | JobStatus::Active) {
+            return Err(format!("Download {} is not running", id));
+        }
+        job.status = JobStatus::Paused;
+        self.save();
+        Ok(previous)
+    }
+
+    /// Puts a paused job back in line; it continues its partial files when started.
+    pub fn resume(&mut self, id: &str) -> Result<(), String> {
+        let job = self.jobs.iter_mut().find(|j| j.id == id).ok_or_else(|| format!("Unknown download {}", id))?;
+        if job.status != JobStatus::Paused {
+            return Err(format!("Download {} is not paused", id));
+        }
+        job.status = JobStatus::Pending;
+        job.args.resume = true;
+        self.save();
+        Ok(())
+    }
+
+    /// Current status of a specific run of a job.
+    pub fn status(&self, id: &str, run: u64) -> Option<JobStatus> {
+        self.jobs.iter().find(|j| j.id == id && j.run == run).map(|j| j.status)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        if self.jobs.iter().any(|j| j.id == id && j.status == JobStatus::Active) {
+            return Err("Cannot remove a running download; cancel it first".into());
+        }
+        self.jobs.retain(|j| j.id != id);
+        self.save();
+        Ok(())
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|j| !j.status.is_done());
+        self.save();
+    }
+
+    fn prune(&mut self) {
+        let done = self.jobs.iter().filter(|j| j.status.is_done()).count();
+        let mut excess = done.saturating_sub(MAX_FINISHED_JOBS);
+        self.jobs.retain(|j| {
+            if excess > 0 && j.status.is_done() {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    pub fn interrupted(&self) -> Vec<QueueJob> {
+        self.jobs.iter().filter(|j| j.status == JobStatus::Interrupted).cloned().collect()
+    }
+
+    pub fn job(&self, id: &str) -> Option<QueueJob> {
+        self.jobs.iter().find(|j| j.id == id).cloned()
+    }
+
+    pub fn job_args(&self, id: &str) -> Option<DownloadArgs> {
+        self.jobs.iter().find(|j| j.id == id).map(|j| j.args.clone())
+    }
+
+    fn count(&self, status: JobStatus) -> usize {
+        self.jobs.iter().filter(|j| j.status == status).count()
+    }
+
+    pub fn snapshot(&self, max_concurrent: usize, window: Option<ScheduleWindow>) -> QueueSnapshot {
+        QueueSnapshot {
+            jobs: self.jobs.clone(),
+            max_concurrent,
+            active: self.count(JobStatus::Active),
+            pending: self.count(JobStatus::Pending),
+            paused: self.count(JobStatus::Paused),
+            window,
+        }
+    }
+}
+
+pub fn emit_state(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let (max_concurrent, window) = {
+        let settings = state.settings.lock().unwrap();
+        (settings.max_concurrent, schedule::current(&settings.schedule))
+    };
+    let snapshot = state.queue.lock().unwrap().snapshot(max_concurrent, window);
+    let _ = app.emit("queue-updated", snapshot);
+}
+
+/// Starts pending jobs until the concurrency limit is reached, unless a schedule
+/// window pauses the queue.
+pub fn pump(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let max_concurrent = {
+        let settings = state.settings.lock().unwrap();
+        match schedule::current(&settings.schedule) {
+            Some(window) if window.action == WindowAction::Pause => 0,
+            _ => settings.max_concurrent,
+        }
+    };
+    let runnable = state.queue.lock().unwrap().take_runnable(max_concurrent);
+
+    for job in runnable {
+        run_job(app, job.args, job.run);
+    }
+    emit_state(app);
+}
+
+/// Starts the process for an active job; also used to restart it with different
+/// arguments (e.g. a software-encoder fallback) without leaving the active state.
+pub fn run_job(app: &AppHandle, args: DownloadArgs, run: u64) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let id = args.id.clone();
+        if let Err(e) = crate::launch_download(&app, args, run).await {
+            let _ = app.emit("download-error", crate::ErrorPayload::new(id.clone(), e.clone()));
+            if let Some(job) = finish(&app, &id, run, JobOutcome::Failed(e)) {
+                crate::record_history(&app, &job, None, None);
+            }
+        }
+    });
+}
+
+/// Called by the process runner once a job's process has exited. Returns the job's
+/// final record unless a newer run of the same job has taken over.
+pub fn finish(app: &AppHandle, id: &str, run: u64, outcome: JobOutcome) -> Option<QueueJob> {
+    let state = app.state::<AppState>();
+    state.journal.lock().unwrap().complete(id, run);
+    let job = state.queue.lock().unwrap().complete(id, run, outcome);
+    pump(app);
+    job
+}
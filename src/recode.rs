@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// When a video download is re-encoded to reach the requested container.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RecodePolicy {
+    /// Only ever change the container, even if players may not handle the codecs.
+    Never,
+    /// Remux when the selected streams fit the container, transcode otherwise.
+    #[default]
+    Auto,
+    /// Always transcode (the old behaviour).
+    Always,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Conversion {
+    /// `--remux-video`: copy the streams into the target container.
+    Remux,
+    /// `--recode-video`: re-encode with ffmpeg.
+    Recode,
+}
+
+fn video_family(vcodec: &str) -> &str {
+    let vcodec = vcodec.to_lowercase();
+    if vcodec.starts_with("avc") || vcodec.starts_with("h264") {
+        "h264"
+    } else if vcodec.starts_with("hvc") || vcodec.starts_with("hev") || vcodec.starts_with("h265") {
+        "h265"
+    } else if vcodec.starts_with("av01") || vcodec == "av1" {
+        "av1"
+    } else if vcodec.starts_with("vp09") || vcodec.starts_with("vp9") {
+        "vp9"
+    } else if vcodec.starts_with("vp8") {
+        "vp8"
+    } else {
+        "other"
+    }
+}
+
+fn audio_family(acodec: &str) -> &str {
+    let acodec = acodec.to_lowercase();
+    if acodec.starts_with("mp4a") || acodec.starts_with("aac") {
+        "aac"
+    } else if acodec.starts_with("opus") {
+        "opus"
+    } else if acodec.starts_with("vorbis") {
+        "vorbis"
+    } else if acodec.starts_with("mp3") {
+        "mp3"
+    } else if acodec.starts_with("ac-3") || acodec.starts_with("ac3") || acodec.starts_with("ec-3") || acodec.starts_with("eac3") {
+        "ac3"
+    } else {
+        "other"
+    }
+}
+
+/// Whether the streams can be copied into `container` and still play in common players.
+pub fn compatible(container: &str, vcodec: &str, acodec: Option<&str>) -> bool {
+    let (videos, audios): (&[&str], &[&str]) = match container {
+        "mkv" => return true,
+        "mp4" | "m4v" | "mov" => (&["h264", "h265", "av1"], &["aac", "mp3", "ac3"]),
+        "webm" => (&["vp8", "vp9", "av1"], &["opus", "vorbis"]),
+        "flv" => (&["h264"], &["aac", "mp3"]),
+        "avi" => (&["h264"], &["mp3", "ac3"]),
+        _ => return false,
+    };
+    videos.contains(&video_family(vcodec)) && acodec.is_none_or(|a| audios.contains(&audio_family(a)))
+}
+
+/// Height cap of the renderer's quality strings, e.g. `bestvideo[height<=720]+bestaudio/...`.
+/// `Some(None)` means uncapped; `None` means the selector is not one we can emulate.
+fn height_cap(quality: &str) -> Option<Option<u64>> {
+    let first = quality.split(['/', '+']).next()?.trim();
+    match first {
+        "bestvideo" | "best" | "bv" | "b" | "bv*" => Some(None),
+        _ => {
+            let cap = first.strip_prefix("bestvideo[height<=")?.strip_suffix(']')?;
+            cap.parse().ok().map(Some)
+        }
+    }
+}
+
+fn codec<'a>(format: &'a Value, key: &str) -> Option<&'a str> {
+    format.get(key).and_then(Value::as_str).filter(|c| *c != "none")
+}
+
+fn number(format: &Value, key: &str) -> f64 {
+    format.get(key).and_then(Value::as_f64).unwrap_or(0.0)
+}
+
+/// Approximates the formats yt-dlp picks with `-S res,vcodec:h264,acodec:aac` for the
+/// renderer's quality strings: the video format and, when it has no audio, the audio one.
+pub fn selected_formats<'a>(info: &'a Value, quality: &str) -> Option<(&'a Value, Option<&'a Value>)> {
+    let cap = height_cap(quality)?;
+    let formats = info.get("formats")?.as_array()?;
+    let fits = |f: &&Value| cap.is_none_or(|cap| number(f, "height") as u64 <= cap);
+
+    let video_rank = |f: &Value| {
+        let h264 = codec(f, "vcodec").is_some_and(|c| video_family(c) == "h264");
+        (number(f, "height") as u64, h264, number(f, "tbr") as u64)
+    };
+    let best_video = formats
+        .iter()
+        .filter(|f| codec(f, "vcodec").is_some() && codec(f, "acodec").is_none())
+        .filter(fits)
+        .max_by_key(|f| video_rank(f));
+    let best_audio = formats
+        .iter()
+        .filter(|f| codec(f, "acodec").is_some() && codec(f, "vcodec").is_none())
+        .max_by_key(|f| {
+            let aac = codec(f, "acodec").is_some_and(|c| audio_family(c) == "aac");
+            (aac, number(f, "abr") as u64)
+        });
+
+    if let Some(video) = best_video {
+        return Some((video, best_audio));
+    }
+
+    // Sites that only serve muxed formats.
+    let combined = formats
+        .iter()
+        .filter(|f| codec(f, "vcodec").is_some() && codec(f, "acodec").is_some())
+        .filter(fits)
+        .max_by_key(|f| video_rank(f))?;
+    Some((combined, None))
+}
+
+/// Approximates the format picked by the renderer's audio selectors (`bestaudio/best`,
+/// `bestaudio[abr<=N]/bestaudio`); `None` for any other selector.
+pub fn selected_audio<'a>(info: &'a Value, quality: &str) -> Option<&'a Value> {
+    let first = quality.split('/').next()?.trim();
+    let cap = match first {
+        "bestaudio" | "ba" => None,
+        _ => Some(first.strip_prefix("bestaudio[abr<=")?.strip_suffix(']')?.parse::<f64>().ok()?),
+    };
+    let formats = info.get("formats")?.as_array()?;
+    let audio_only = || formats.iter().filter(|f| codec(f, "acodec").is_some() && codec(f, "vcodec").is_none());
+    let by_bitrate = |f: &&Value| number(f, "abr") as u64;
+    audio_only()
+        .filter(|f| cap.is_none_or(|cap| number(f, "abr") <= cap))
+        .max_by_key(by_bitrate)
+        // The `/bestaudio` fallback when nothing is under the cap.
+        .or_else(|| audio_only().max_by_key(by_bitrate))
+}
+
+/// Video and audio codecs of `selected_formats`.
+pub fn selected_codecs(info: &Value, quality: &str) -> Option<(String, Option<String>)> {
+    let (video, audio) = selected_formats(info, quality)?;
+    let vcodec = codec(video, "vcodec")?.to_string();
+    let acodec = codec(audio.unwrap_or(video), "acodec").map(String::from);
+    Some((vcodec, acodec))
+}
+
+/// yt-dlp arguments that merge the selected streams and bring the result into
+/// `container` with `conversion`. yt-dlp's VideoConvertor skips files that already have
+/// the target extension, so a transcode merges into another container first (mkv, or
+/// mp4 when mkv is the target) and converts from there.
+pub fn container_args(conversion: Conversion, container: &str) -> Vec<String> {
+    let (merge_into, flag) = match conversion {
+        Conversion::Remux => (container, "--remux-video"),
+        Conversion::Recode if container == "mkv" => ("mp4", "--recode-video"),
+        Conversion::Recode => ("mkv", "--recode-video"),
+    };
+    vec!["--merge-output-format".into(), merge_into.into(), flag.into(), container.into()]
+}
+
+/// Picks remux or transcode for a video job given the selected video/audio codecs.
+/// When those are unknown, `Auto` transcodes so the result is guaranteed to play.
+pub fn decide(policy: RecodePolicy, selected: Option<(String, Option<String>)>, container: &str) -> Conversion {
+    match policy {
+        RecodePolicy::Never => Conversion::Remux,
+        RecodePolicy::Always => Conversion::Recode,
+        RecodePolicy::Auto => match selected {
+            Some((vcodec, acodec)) if compatible(container, &vcodec, acodec.as_deref()) => {
+                println!("[Recode] {} / {:?} fit .{}, remuxing only", vcodec, acodec, container);
+                Conversion::Remux
+            }
+            Some((vcodec, acodec)) => {
+                println!("[Recode] {} / {:?} do not fit .{}, transcoding", vcodec, acodec, container);
+                Conversion::Recode
+            }
+            None => Conversion::Recode,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn transcodes_merge_into_another_container_first() {
+        // vp9/opus cannot be copied into mp4, so the job is transcoded.
+        let info = json!({ "formats": [
+            { "format_id": "248", "vcodec": "vp9", "acodec": "none", "height": 1080, "tbr": 2000 },
+            { "format_id": "251", "vcodec": "none", "acodec": "opus", "abr": 160 },
+        ]});
+        let conversion = decide(RecodePolicy::Auto, selected_codecs(&info, "bestvideo+bestaudio/best"), "mp4");
+        assert_eq!(conversion, Conversion::Recode);
+        assert_eq!(
+            container_args(conversion, "mp4"),
+            args(&["--merge-output-format", "mkv", "--recode-video", "mp4"])
+        );
+        assert_eq!(
+            container_args(Conversion::Recode, "mkv"),
+            args(&["--merge-output-format", "mp4", "--recode-video", "mkv"])
+        );
+    }
+
+    #[test]
+    fn remuxes_merge_straight_into_the_target() {
+        let info = json!({ "formats": [
+            { "format_id": "137", "vcodec": "avc1.640028", "acodec": "none", "height": 1080, "tbr": 4000 },
+            { "format_id": "140", "vcodec": "none", "acodec": "mp4a.40.2", "abr": 128 },
+        ]});
+        let conversion = decide(RecodePolicy::Auto, selected_codecs(&info, "bestvideo+bestaudio/best"), "mp4");
+        assert_eq!(conversion, Conversion::Remux);
+        assert_eq!(
+            container_args(conversion, "mp4"),
+            args(&["--merge-output-format", "mp4", "--remux-video", "mp4"])
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::errors::ErrorCode;
+
+/// Browsers yt-dlp can read cookies from.
+const BROWSERS: &[&str] = &["brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale"];
+
+/// When and how failed downloads are re-run automatically.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Total runs of a job, including the first; 1 disables retries.
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for every further one.
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+    /// Error classes worth retrying; others fail right away.
+    pub retry_on: Vec<ErrorCode>,
+    /// Browser to take cookies from on retries when no cookies were extracted.
+    pub cookies_from_browser: Option<String>,
+    /// YouTube player clients to cycle through on retries (e.g. `web_safari`, `android`).
+    pub player_clients: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_delay_secs: 10,
+            max_delay_secs: 300,
+            retry_on: vec![ErrorCode::RateLimited, ErrorCode::NetworkTimeout, ErrorCode::Forbidden],
+            cookies_from_browser: None,
+            player_clients: Vec::new(),
+        }
+    }
+}
+
+/// Announces an automatic re-run, emitted as `download-retrying`.
+#[derive(Serialize, Clone)]
+pub struct Retrying {
+    pub id: String,
+    /// The upcoming attempt, 2 for the first retry.
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub code: ErrorCode,
+    pub reason: String,
+    pub delay_secs: u64,
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 || self.max_attempts > 10 {
+            return Err("Retry attempts must be between 1 and 10".into());
+        }
+        if self.max_delay_secs < self.initial_delay_secs {
+            return Err("The maximum retry delay cannot be shorter than the initial one".into());
+        }
+        if let Some(browser) = &self.cookies_from_browser {
+            if !BROWSERS.contains(&browser.as_str()) {
+                return Err(format!("Unsupported browser for cookies: {:?}", browser));
+            }
+        }
+        if let Some(client) = self.player_clients.iter().find(|c| c.is_empty() || !c.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_')) {
+            return Err(format!("Invalid player client: {:?}", client));
+        }
+        Ok(())
+    }
+
+    /// Whether a job that failed with `code` on run `attempt` (1-based) runs again.
+    pub fn should_retry(&self, code: ErrorCode, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&code)
+    }
+
+    /// Wait before run `attempt` (2 for the first retry).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(2).min(16);
+        let secs = self.initial_delay_secs.saturating_mul(1 << doublings);
+        Duration::from_secs(secs.min(self.max_delay_secs))
+    }
+
+    /// Extra yt-dlp arguments for run `attempt`; the first run gets none.
+    pub fn push_args(&self, attempt: u32, has_cookie_file: bool, args: &mut Vec<String>) {
+        if attempt < 2 {
+            return;
+        }
+        if let Some(browser) = self.cookies_from_browser.as_ref().filter(|_| !has_cookie_file) {
+            args.extend(["--cookies-from-browser".into(), browser.clone()]);
+        }
+        if !self.player_clients.is_empty() {
+            let client = &self.player_clients[(attempt as usize - 2) % self.player_clients.len()];
+            args.extend(["--extractor-args".into(), format!("youtube:player_client={}", client)]);
+        }
+    }
+}
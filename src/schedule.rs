@@ -0,0 +1,115 @@
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{limits, queue, AppState};
+
+/// How often the schedule is re-evaluated.
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowAction {
+    /// No new downloads start while the window is open. Downloads already running when
+    /// it opens are not stopped and finish normally.
+    Pause,
+    /// Downloads started during the window share `rate_limit`, each capped at
+    /// `rate_limit / max_concurrent`. Downloads already running when it opens keep
+    /// their rate until they finish or are restarted.
+    Throttle,
+}
+
+/// A recurring time-of-day window, in local time. Windows whose end is before their
+/// start run past midnight into the next day.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleWindow {
+    /// `HH:MM`.
+    pub start: String,
+    /// `HH:MM`.
+    pub end: String,
+    /// ISO weekdays the window starts on (1 = Monday ... 7 = Sunday); every day when empty.
+    #[serde(default)]
+    pub days: Vec<u32>,
+    pub action: WindowAction,
+    /// Total rate for all downloads while a `Throttle` window is active.
+    #[serde(default)]
+    pub rate_limit: Option<String>,
+}
+
+fn parse_clock(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl ScheduleWindow {
+    pub fn validate(&self) -> Result<(), String> {
+        let start = parse_clock(&self.start).ok_or_else(|| format!("Invalid window start: {:?}", self.start))?;
+        let end = parse_clock(&self.end).ok_or_else(|| format!("Invalid window end: {:?}", self.end))?;
+        if start == end {
+            return Err(format!("Window {}-{} is empty", self.start, self.end));
+        }
+        if let Some(day) = self.days.iter().find(|d| !(1..=7).contains(*d)) {
+            return Err(format!("Invalid weekday {} (use 1 = Monday to 7 = Sunday)", day));
+        }
+        if self.action == WindowAction::Throttle {
+            match &self.rate_limit {
+                Some(rate) if limits::parse_rate(rate).is_some() => {}
+                _ => return Err(format!("Throttle window {}-{} needs a valid rate limit", self.start, self.end)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the window covers `minute` (minutes since midnight) on ISO `weekday`.
+    fn contains(&self, weekday: u32, minute: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_clock(&self.start), parse_clock(&self.end)) else {
+            return false;
+        };
+        let starts_on = |day: u32| self.days.is_empty() || self.days.contains(&day);
+        if start < end {
+            starts_on(weekday) && minute >= start && minute < end
+        } else {
+            let previous = if weekday == 1 { 7 } else { weekday - 1 };
+            (starts_on(weekday) && minute >= start) || (starts_on(previous) && minute < end)
+        }
+    }
+}
+
+/// The window in effect right now; when several overlap, pausing wins over throttling
+/// and otherwise the first listed applies.
+pub fn current(windows: &[ScheduleWindow]) -> Option<ScheduleWindow> {
+    let now = Local::now();
+    let (weekday, minute) = (now.weekday().number_from_monday(), now.hour() * 60 + now.minute());
+    let active: Vec<&ScheduleWindow> = windows.iter().filter(|w| w.contains(weekday, minute)).collect();
+    active
+        .iter()
+        .find(|w| w.action == WindowAction::Pause)
+        .or(active.first())
+        .map(|w| (*w).clone())
+}
+
+/// Re-evaluates the schedule periodically so the queue resumes (or starts throttling)
+/// when a window opens or closes, even if nothing else happens.
+pub fn start_ticker(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last: Option<ScheduleWindow> = None;
+        loop {
+            let window = {
+                let state = app.state::<AppState>();
+                let settings = state.settings.lock().unwrap();
+                current(&settings.schedule)
+            };
+            if window != last {
+                println!("[Schedule] Active window: {:?}", window);
+                let _ = app.emit("schedule-changed", window.clone());
+                last = window;
+                queue::pump(&app);
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
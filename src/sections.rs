@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Added before `.%(ext)s` so every section lands in its own, recognisable file.
+const SECTION_SUFFIX: &str = " [%(section_start>%H-%M-%S)s-%(section_end>%H-%M-%S)s]";
+
+/// A clip given as timestamps (`90`, `1:30`, `01:01:30.5`). An empty end means
+/// "until the end of the video".
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct TimeRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// Parts of a video to download instead of the whole thing.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SectionOptions {
+    pub ranges: Vec<TimeRange>,
+    /// Chapter title patterns (regular expressions, matched by yt-dlp).
+    pub chapters: Vec<String>,
+    /// Re-encode around the cut points so clips start exactly on time.
+    pub force_keyframes: bool,
+}
+
+/// Parses `SS`, `MM:SS` or `HH:MM:SS`, each with optional fractional seconds.
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let parts: Vec<&str> = text.split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    // Plain digits only: `parse` alone would also take "inf", "NaN", "1e9" or "-5".
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let (whole, fraction) = match part.split_once('.') {
+            Some((whole, fraction)) if i == parts.len() - 1 => (whole, Some(fraction)),
+            Some(_) => return None,
+            None => (*part, None),
+        };
+        if !is_digits(whole) || fraction.is_some_and(|f| !is_digits(f)) {
+            return None;
+        }
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    seconds.is_finite().then_some(seconds)
+}
+
+/// Chapter patterns that contain no regex syntax can be checked against the chapter list.
+fn is_plain_text(pattern: &str) -> bool {
+    !pattern.chars().any(|c| "\\^$.|?*+()[]{}".contains(c))
+}
+
+impl SectionOptions {
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty() && self.chapters.is_empty()
+    }
+
+    /// Checks the ranges (and plain-text chapter names) against the video's `-J` info,
+    /// when it is known.
+    pub fn validate(&self, info: Option<&Value>) -> Result<(), String> {
+        let duration = info.and_then(|i| i.get("duration")).and_then(Value::as_f64);
+
+        for range in &self.ranges {
+            let start = parse_timestamp(&range.start)
+                .ok_or_else(|| format!("Invalid start time: {:?}", range.start))?;
+            let end = if range.end.trim().is_empty() {
+                None
+            } else {
+                Some(parse_timestamp(&range.end).ok_or_else(|| format!("Invalid end time: {:?}", range.end))?)
+            };
+            if end.is_some_and(|end| end <= start) {
+                return Err(format!("Section {}-{} ends before it starts", range.start, range.end));
+            }
+            if let Some(duration) = duration {
+                if start >= duration {
+                    return Err(format!("Section start {} is past the end of the video ({}s)", range.start, duration));
+                }
+                // yt-dlp rounds durations to whole seconds.
+                if end.is_some_and(|end| end > duration + 1.0) {
+                    return Err(format!("Section end {} is past the end of the video ({}s)", range.end, duration));
+                }
+            }
+        }
+
+        for pattern in &self.chapters {
+            if pattern.trim().is_empty() || pattern.starts_with('*') {
+                return Err(format!("Invalid chapter pattern: {:?}", pattern));
+            }
+            let Some(info) = info else { continue };
+            if !is_plain_text(pattern) {
+                continue;
+            }
+            let titles: Vec<&str> = info
+                .get("chapters")
+                .and_then(Value::as_array)
+                .map(|chapters| chapters.iter().filter_map(|c| c.get("title").and_then(Value::as_str)).collect())
+                .unwrap_or_default();
+            if titles.is_empty() {
+                return Err("This video has no chapters to select".into());
+            }
+            let needle = pattern.to_lowercase();
+            if !titles.iter().any(|t| t.to_lowercase().contains(&needle)) {
+                return Err(format!("No chapter matches {:?}", pattern));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn push_args(&self, args: &mut Vec<String>) {
+        for range in &self.ranges {
+            let start = parse_timestamp(&range.start).unwrap_or(0.0);
+            let end = parse_timestamp(&range.end).map(|e| e.to_string()).unwrap_or_else(|| "inf".into());
+            args.extend(["--download-sections".into(), format!("*{}-{}", start, end)]);
+        }
+        for pattern in &self.chapters {
+            args.extend(["--download-sections".into(), pattern.clone()]);
+        }
+        if self.force_keyframes && !self.is_empty() {
+            args.push("--force-keyframes-at-cuts".into());
+        }
+    }
+}
+
+/// Adds the section start/end to an output template, right before its extension.
+pub fn with_section_suffix(template: &str) -> String {
+    match template.rfind(".%(ext)s") {
+        Some(pos) => format!("{}{}{}", &template[..pos], SECTION_SUFFIX, &template[pos..]),
+        None => format!("{}{}", template, SECTION_SUFFIX),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("90"), Some(90.0));
+        assert_eq!(parse_timestamp(" 1:30 "), Some(90.0));
+        assert_eq!(parse_timestamp("01:02:03"), Some(3723.0));
+        assert_eq!(parse_timestamp("1:02.5"), Some(62.5));
+        assert_eq!(parse_timestamp("0.25"), Some(0.25));
+    }
+
+    #[test]
+    fn rejects_non_numeric_timestamps() {
+        for text in ["", " ", "inf", "NaN", "1e9", "-5", "+5", "1:-2", "1::2", "1:2:3:4", "1.5:30", "1.", ".5", "1:2.3.4", "abc"] {
+            assert_eq!(parse_timestamp(text), None, "{:?}", text);
+        }
+    }
+}
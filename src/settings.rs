@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::archive::ArchiveMode;
+use crate::encoders::VideoCodec;
+use crate::limits::TransferLimits;
+use crate::metadata::EmbedOptions;
+use crate::presets::{self, EncodingPreset};
+use crate::recode::RecodePolicy;
+use crate::retry::RetryPolicy;
+use crate::schedule::ScheduleWindow;
+use crate::transfer::TransferSettings;
+use crate::{sponsorblock, storage, template};
+
+/// Backend-owned preferences, persisted as `settings.json` in the app data folder.
+/// Missing fields fall back to their defaults so older files keep loading.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Upper bound on yt-dlp processes running at the same time.
+    pub max_concurrent: usize,
+    /// Whether finished downloads are recorded in a yt-dlp `--download-archive`.
+    pub archive_mode: ArchiveMode,
+    /// yt-dlp `-o` template, relative to the download folder; may contain subfolders.
+    pub output_template: String,
+    /// Default metadata/chapter/cover-art embedding for new jobs.
+    pub embed: EmbedOptions,
+    /// SponsorBlock server to query instead of the public one.
+    pub sponsorblock_api: Option<String>,
+    /// When video downloads are transcoded rather than just remuxed.
+    pub recode_policy: RecodePolicy,
+    /// Codec used when a transcode is needed.
+    pub video_codec: VideoCodec,
+    /// Preferred encoder (e.g. `hevc_nvenc`); the best verified one when unset.
+    pub encoder: Option<String>,
+    /// Name of the preset used for transcodes unless a job picks another.
+    pub encoding_preset: String,
+    pub custom_presets: Vec<EncodingPreset>,
+    /// Bandwidth limits. The rate limit is a total for all downloads, applied as a cap of
+    /// `rate_limit / max_concurrent` on each one.
+    pub limits: TransferLimits,
+    /// Time-of-day windows that pause or throttle the queue.
+    pub schedule: Vec<ScheduleWindow>,
+    /// Automatic re-runs of downloads that failed for transient reasons.
+    pub retry: RetryPolicy,
+    /// Timeouts and retries for dependency and app update downloads.
+    pub transfer: TransferSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            max_concurrent: 3,
+            archive_mode: ArchiveMode::Off,
+            output_template: template::DEFAULT_TEMPLATE.into(),
+            embed: EmbedOptions::default(),
+            sponsorblock_api: None,
+            recode_policy: RecodePolicy::Auto,
+            video_codec: VideoCodec::H264,
+            encoder: None,
+            encoding_preset: presets::DEFAULT_PRESET.into(),
+            custom_presets: Vec::new(),
+            limits: TransferLimits::default(),
+            schedule: Vec::new(),
+            retry: RetryPolicy::default(),
+            transfer: TransferSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Settings {
+        storage::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        storage::save_json(path, self)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent == 0 {
+            return Err("max_concurrent must be at least 1".into());
+        }
+        template::validate(&self.output_template)?;
+        self.embed.validate()?;
+        if let Some(api) = &self.sponsorblock_api {
+            sponsorblock::validate_api_url(api)?;
+        }
+        let built_in = presets::built_in();
+        for (i, preset) in self.custom_presets.iter().enumerate() {
+            preset.validate()?;
+            let taken = built_in.iter().chain(&self.custom_presets[..i]).any(|p| p.name == preset.name);
+            if taken {
+                return Err(format!("An encoding preset named {:?} already exists", preset.name));
+            }
+        }
+        self.limits.validate()?;
+        for window in &self.schedule {
+            window.validate()?;
+        }
+        self.retry.validate()?;
+        self.transfer.validate()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// Categories understood by yt-dlp, plus its `all` shorthand.
+const CATEGORIES: &[&str] = &[
+    "all", "sponsor", "intro", "outro", "selfpromo", "preview", "filler",
+    "interaction", "music_offtopic", "poi_highlight", "chapter",
+];
+
+/// Point-like or whole-chapter categories that can be marked but not cut out.
+const MARK_ONLY: &[&str] = &["poi_highlight", "chapter"];
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SponsorBlockMode {
+    /// Cut the segments out of the file.
+    #[default]
+    Remove,
+    /// Keep the segments and add them as chapters.
+    Mark,
+}
+
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SponsorBlockOptions {
+    /// Category names; a leading `-` excludes one (e.g. `all,-filler`).
+    pub categories: Vec<String>,
+    pub mode: SponsorBlockMode,
+}
+
+impl SponsorBlockOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        for category in &self.categories {
+            let name = category.trim().strip_prefix('-').unwrap_or(category.trim());
+            if !CATEGORIES.contains(&name) {
+                return Err(format!("Unknown SponsorBlock category: {:?}", category));
+            }
+            if self.mode == SponsorBlockMode::Remove && MARK_ONLY.contains(&name) && !category.trim().starts_with('-') {
+                return Err(format!("SponsorBlock category {:?} can only be marked, not removed", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends `--sponsorblock-remove`/`--sponsorblock-mark`; `api` replaces the public
+    /// SponsorBlock server (e.g. a local mirror).
+    pub fn push_args(&self, download_type: &str, api: Option<&str>, args: &mut Vec<String>) {
+        if self.categories.is_empty() || download_type == "thumbnail" {
+            return;
+        }
+        let flag = match self.mode {
+            SponsorBlockMode::Remove => "--sponsorblock-remove",
+            SponsorBlockMode::Mark => "--sponsorblock-mark",
+        };
+        let categories: Vec<&str> = self.categories.iter().map(|c| c.trim()).collect();
+        args.extend([flag.into(), categories.join(",")]);
+        if let Some(api) = api {
+            args.extend(["--sponsorblock-api".into(), api.trim_end_matches('/').into()]);
+        }
+    }
+}
+
+pub fn validate_api_url(url: &str) -> Result<(), String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(format!("SponsorBlock API URL must start with http:// or https://: {:?}", url))
+    }
+}
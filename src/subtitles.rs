@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+}
+
+/// Subtitle part of a download request. Nothing is fetched unless `enabled` is set.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SubtitleOptions {
+    pub enabled: bool,
+    /// yt-dlp `--sub-langs` entries, e.g. `en`, `ar`, `en.*`, or `-live_chat` to exclude;
+    /// yt-dlp's default (English) when empty.
+    pub languages: Vec<String>,
+    /// Also accept auto-generated captions when no manual track exists.
+    pub auto_generated: bool,
+    /// Convert to this format; the site's format is kept when unset.
+    pub format: Option<SubtitleFormat>,
+    /// Mux into the video file instead of writing sidecar files.
+    pub embed: bool,
+}
+
+impl SubtitleOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        for lang in &self.languages {
+            let body = lang.strip_prefix('-').unwrap_or(lang);
+            let valid = !body.is_empty()
+                && body.chars().all(|c| c.is_ascii_alphanumeric() || "-_.*+".contains(c));
+            if !valid {
+                return Err(format!("Invalid subtitle language: {:?}", lang));
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the yt-dlp flags for these options. Subtitles can only be embedded
+    /// into video downloads; other types get sidecar files.
+    pub fn push_args(&self, download_type: &str, args: &mut Vec<String>) {
+        if !self.enabled {
+            return;
+        }
+        args.push("--write-subs".into());
+        if self.auto_generated {
+            args.push("--write-auto-subs".into());
+        }
+        if !self.languages.is_empty() {
+            args.extend(["--sub-langs".into(), self.languages.join(",")]);
+        }
+        if let Some(format) = self.format {
+            args.extend(["--convert-subs".into(), format.as_str().into()]);
+        }
+        if self.embed && download_type == "video" {
+            args.push("--embed-subs".into());
+        }
+    }
+}
+
+/// A subtitle track offered by the site, as listed in the `get_video_info` response.
+#[derive(Serialize)]
+pub struct SubtitleTrack {
+    pub lang: String,
+    pub name: Option<String>,
+    /// Auto-generated (or machine-translated) captions.
+    pub auto: bool,
+    /// Formats the site serves, e.g. `vtt`, `srv3`, `json3`.
+    pub formats: Vec<String>,
+}
+
+fn collect(tracks: &mut Vec<SubtitleTrack>, info: &Value, key: &str, auto: bool) {
+    let Some(langs) = info.get(key).and_then(Value::as_object) else {
+        return;
+    };
+    for (lang, variants) in langs {
+        // Chat replays are listed as subtitles but are not captions.
+        if lang == "live_chat" {
+            continue;
+        }
+        let variants = variants.as_array().map(Vec::as_slice).unwrap_or_default();
+        let name = variants.iter().find_map(|v| v.get("name").and_then(Value::as_str)).map(String::from);
+        let formats = variants.iter().filter_map(|v| v.get("ext").and_then(Value::as_str)).map(String::from).collect();
+        tracks.push(SubtitleTrack { lang: lang.clone(), name, auto, formats });
+    }
+}
+
+/// Manual tracks first, then auto-generated ones, each sorted by language code.
+pub fn available_tracks(info: &Value) -> Vec<SubtitleTrack> {
+    let mut manual = Vec::new();
+    collect(&mut manual, info, "subtitles", false);
+    manual.sort_by(|a, b| a.lang.cmp(&b.lang));
+
+    let mut auto = Vec::new();
+    collect(&mut auto, info, "automatic_captions", true);
+    auto.sort_by(|a, b| a.lang.cmp(&b.lang));
+
+    manual.extend(auto);
+    manual
+}
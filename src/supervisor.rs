@@ -0,0 +1,402 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+
+use crate::{progress, AppState, ChildProcess};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// How many trailing non-empty lines of each stream are kept for error reports.
+const TAIL_LINES: usize = 15;
+
+/// How long an interrupted process gets to clean up before its process tree is killed.
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How often `terminate` checks whether the process has exited.
+const EXIT_POLL: Duration = Duration::from_millis(100);
+
+/// A line of output from a supervised process.
+pub enum Line {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// Output of a process that was run to completion.
+pub struct Captured {
+    pub code: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl Captured {
+    pub fn success(&self) -> bool {
+        self.code == 0
+    }
+
+    /// Stdout followed by stderr, for tools that report on either stream.
+    pub fn combined_text(&self) -> String {
+        let mut text = String::from_utf8_lossy(&self.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&self.stderr));
+        text
+    }
+}
+
+/// How a supervised process ended.
+pub struct Exit {
+    pub code: Option<i32>,
+    pub stdout_tail: Vec<String>,
+    pub stderr_tail: Vec<String>,
+    /// Set when waiting on the process itself failed.
+    pub wait_error: Option<String>,
+}
+
+impl Exit {
+    pub fn success(&self) -> bool {
+        self.wait_error.is_none() && self.code == Some(0)
+    }
+
+    /// The error text reported to the UI, identical on every platform.
+    pub fn error_message(&self) -> String {
+        if let Some(e) = &self.wait_error {
+            e.clone()
+        } else if !self.stderr_tail.is_empty() {
+            self.stderr_tail.join("\n")
+        } else if !self.stdout_tail.is_empty() {
+            format!("Process exited with code {:?}. Last output:\n{}", self.code, self.stdout_tail.join("\n"))
+        } else {
+            format!("Process exited with code {:?} (No output captured)", self.code)
+        }
+    }
+}
+
+/// Builds a command that never opens a console window and optionally prepends
+/// `extra_path` (e.g. the Deno folder) to `PATH`.
+fn command(program: &Path, args: &[String], extra_path: Option<&Path>) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(dir) = extra_path {
+        let path_var = std::env::var("PATH").unwrap_or_default();
+        let path_sep = if cfg!(windows) { ";" } else { ":" };
+        cmd.env("PATH", format!("{}{}{}", dir.to_string_lossy(), path_sep, path_var));
+    }
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    // Own process group, so ffmpeg and other helpers can be signalled along with it.
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+/// Runs a process to completion and captures its output.
+pub async fn run(program: &Path, args: &[String], extra_path: Option<&Path>) -> Result<Captured, String> {
+    let output = command(program, args, extra_path)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {:?}: {}", program, e))?;
+    Ok(Captured {
+        code: output.status.code().unwrap_or(-1),
+        stdout: output.stdout,
+        stderr: output.stderr,
+    })
+}
+
+/// Reads `reader` line by line (lossy UTF-8, CR/LF stripped) until EOF.
+async fn read_lines<R, F>(reader: R, mut on_line: F)
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(String),
+{
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer);
+                on_line(line.trim_end_matches(['\n', '\r']).to_string());
+            }
+            Err(e) => {
+                println!("[Supervisor] Read error: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// Progress-template lines are left out: they say nothing about why a process failed.
+fn push_tail(tail: &mut Vec<String>, line: &str) {
+    if line.trim().is_empty() || progress::is_progress_line(line) {
+        return;
+    }
+    tail.push(line.to_string());
+    if tail.len() > TAIL_LINES {
+        tail.remove(0);
+    }
+}
+
+/// Spawns a long-running process, registers its PID in `AppState.children` under `id`,
+/// streams every output line to `on_line` and resolves once the process has exited
+/// and both streams are drained.
+pub fn spawn<F>(
+    app: &AppHandle,
+    id: &str,
+    program: &Path,
+    args: &[String],
+    extra_path: Option<&Path>,
+    on_line: F,
+) -> Result<tauri::async_runtime::JoinHandle<Exit>, String>
+where
+    F: FnMut(Line) + Send + 'static,
+{
+    let mut child = command(program, args, extra_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start {:?}: {}", program, e))?;
+
+    // Without a PID the process could not be signalled; kill(-0) would hit our own group.
+    let pid = child.id().ok_or_else(|| format!("{:?} exited before its process id could be read", program))?;
+    let stdout = child.stdout.take().ok_or("stdout not captured")?;
+    let stderr = child.stderr.take().ok_or("stderr not captured")?;
+
+    let state = app.state::<AppState>();
+    state.children.lock().unwrap().insert(id.to_string(), ChildProcess { pid });
+
+    let app = app.clone();
+    let id = id.to_string();
+    Ok(tauri::async_runtime::spawn(async move {
+        let exit = supervise(child, stdout, stderr, on_line).await;
+
+        // Only drop our own registration; a resumed job may already own the id again.
+        let state = app.state::<AppState>();
+        let mut children = state.children.lock().unwrap();
+        if children.get(&id).map(|c| c.pid) == Some(pid) {
+            children.remove(&id);
+        }
+        exit
+    }))
+}
+
+/// Streams the output of `child` to `on_line` until it has exited and both streams
+/// are drained.
+async fn supervise<F>(mut child: Child, stdout: ChildStdout, stderr: ChildStderr, mut on_line: F) -> Exit
+where
+    F: FnMut(Line),
+{
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx_err = tx.clone();
+    let stdout_task = read_lines(stdout, move |line| { let _ = tx.send(Line::Stdout(line)); });
+    let stderr_task = read_lines(stderr, move |line| { let _ = tx_err.send(Line::Stderr(line)); });
+
+    // Lines are delivered from one place so the callback sees them in arrival order.
+    let mut stdout_tail = Vec::new();
+    let mut stderr_tail = Vec::new();
+    let deliver = async {
+        while let Some(line) = rx.recv().await {
+            match &line {
+                Line::Stdout(text) => push_tail(&mut stdout_tail, text),
+                Line::Stderr(text) => push_tail(&mut stderr_tail, text),
+            }
+            on_line(line);
+        }
+    };
+
+    let (_, _, _, status) = tokio::join!(stdout_task, stderr_task, deliver, child.wait());
+    match status {
+        Ok(status) => Exit { code: status.code(), stdout_tail, stderr_tail, wait_error: None },
+        Err(e) => Exit { code: None, stdout_tail, stderr_tail, wait_error: Some(e.to_string()) },
+    }
+}
+
+/// Forcefully terminates a process and its whole tree (its process group on Unix).
+/// Does not wait for the processes to go away.
+pub fn kill(pid: u32) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let mut cmd = std::process::Command::new("taskkill");
+        cmd.args(["/F", "/T", "/PID", &pid.to_string()]);
+        cmd.creation_flags(CREATE_NO_WINDOW);
+        let _ = cmd.spawn();
+    }
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+/// Asks a process tree to stop the way Ctrl+C would, so yt-dlp can close its files.
+/// Console processes on Windows cannot be interrupted from outside, so they are killed.
+fn interrupt(pid: u32) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGINT);
+    }
+    #[cfg(windows)]
+    kill(pid);
+}
+
+fn is_running(app: &AppHandle, id: &str, pid: u32) -> bool {
+    let state = app.state::<AppState>();
+    let children = state.children.lock().unwrap();
+    children.get(id).map(|c| c.pid) == Some(pid)
+}
+
+/// Interrupts the process spawned under `id` and waits until it has exited and its
+/// output is drained, killing the whole tree if that takes longer than `GRACE_PERIOD`.
+/// Returns whether the tree had to be killed, which is always the case on Windows.
+pub async fn terminate(app: &AppHandle, id: &str, pid: u32) -> bool {
+    terminate_with(id, pid, GRACE_PERIOD, || is_running(app, id, pid)).await
+}
+
+async fn terminate_with<F>(id: &str, pid: u32, grace: Duration, mut is_running: F) -> bool
+where
+    F: FnMut() -> bool,
+{
+    interrupt(pid);
+    let deadline = Instant::now() + grace;
+    // `interrupt` already killed the tree there.
+    let mut forced = cfg!(windows);
+    while is_running() {
+        if !forced && Instant::now() >= deadline {
+            println!("[Supervisor] {} did not stop within {:?}, killing it", id, grace);
+            kill(pid);
+            forced = true;
+        } else if forced && Instant::now() >= deadline + grace {
+            // Something outside the tree holds its output open; stop waiting.
+            println!("[Supervisor] {} still registered after being killed", id);
+            break;
+        }
+        tokio::time::sleep(EXIT_POLL).await;
+    }
+    // Helpers that ignored the interrupt would otherwise outlive yt-dlp.
+    #[cfg(unix)]
+    if !forced {
+        kill(pid);
+    }
+    forced
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn sh(script: &str) -> Child {
+        command(Path::new("sh"), &["-c".to_string(), script.to_string()], None)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    /// Runs `script` and returns its exit plus every line as `(is_stderr, text)`.
+    async fn run_script(script: &str) -> (Exit, Vec<(bool, String)>) {
+        let mut child = sh(script);
+        let (stdout, stderr) = (child.stdout.take().unwrap(), child.stderr.take().unwrap());
+        let mut lines = Vec::new();
+        let exit = supervise(child, stdout, stderr, |line| {
+            lines.push(match line {
+                Line::Stdout(text) => (false, text),
+                Line::Stderr(text) => (true, text),
+            })
+        })
+        .await;
+        (exit, lines)
+    }
+
+    fn exit(code: Option<i32>, stdout: &[&str], stderr: &[&str], wait_error: Option<&str>) -> Exit {
+        Exit {
+            code,
+            stdout_tail: stdout.iter().map(|s| s.to_string()).collect(),
+            stderr_tail: stderr.iter().map(|s| s.to_string()).collect(),
+            wait_error: wait_error.map(String::from),
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_both_streams_in_order_without_line_endings() {
+        let (exit, lines) = run_script("printf 'one\\r\\n'; sleep 0.2; printf 'two\\r\\n' >&2; sleep 0.2; printf 'three\\n'").await;
+        assert!(exit.success());
+        assert_eq!(lines, vec![(false, "one".into()), (true, "two".into()), (false, "three".into())]);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_last_non_empty_lines() {
+        let (exit, lines) = run_script("for i in $(seq 1 20); do echo line$i; echo; done").await;
+        assert_eq!(lines.len(), 40);
+        let expected: Vec<String> = (6..=20).map(|i| format!("line{}", i)).collect();
+        assert_eq!(exit.stdout_tail, expected);
+        assert!(exit.stderr_tail.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_progress_lines_out_of_the_tail() {
+        let script = "echo '[hmd-progress] postprocess|started|Merger' >&2; echo 'ERROR: Postprocessing: Conversion failed!' >&2; exit 1";
+        let (exit, lines) = run_script(script).await;
+        assert_eq!(lines[0], (true, "[hmd-progress] postprocess|started|Merger".into()));
+        assert_eq!(exit.stderr_tail, vec!["ERROR: Postprocessing: Conversion failed!".to_string()]);
+        assert_eq!(exit.error_message(), "ERROR: Postprocessing: Conversion failed!");
+    }
+
+    #[tokio::test]
+    async fn propagates_the_exit_code() {
+        let (exit, _) = run_script("echo progress; echo boom >&2; exit 3").await;
+        assert_eq!(exit.code, Some(3));
+        assert!(!exit.success());
+        assert_eq!(exit.error_message(), "boom");
+    }
+
+    #[test]
+    fn error_message_falls_back_in_order() {
+        assert_eq!(exit(Some(1), &["out"], &["err"], Some("wait failed")).error_message(), "wait failed");
+        assert_eq!(exit(Some(1), &["out"], &["a", "b"], None).error_message(), "a\nb");
+        assert_eq!(exit(Some(2), &["x", "y"], &[], None).error_message(), "Process exited with code Some(2). Last output:\nx\ny");
+        assert_eq!(exit(None, &[], &[], None).error_message(), "Process exited with code None (No output captured)");
+        assert!(!exit(Some(0), &[], &[], Some("wait failed")).success());
+    }
+
+    /// Spawns `script`, waits for its first output line (so signal handlers are in place)
+    /// and returns its PID plus a flag cleared once it has exited.
+    async fn watched(script: &str) -> (u32, Arc<AtomicBool>) {
+        let mut child = sh(script);
+        let pid = child.id().unwrap();
+        let mut ready = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut ready).await.unwrap();
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        tokio::spawn(async move {
+            let _ = child.wait().await;
+            flag.store(false, Ordering::SeqCst);
+        });
+        (pid, running)
+    }
+
+    #[tokio::test]
+    async fn terminate_interrupts_a_cooperative_process() {
+        let (pid, running) = watched("echo ready; exec sleep 30").await;
+        let forced = terminate_with("test", pid, Duration::from_secs(5), || running.load(Ordering::SeqCst)).await;
+        assert!(!forced);
+        assert!(!running.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn terminate_kills_a_process_ignoring_the_interrupt() {
+        let (pid, running) = watched("trap '' INT; echo ready; sleep 30").await;
+        let started = Instant::now();
+        let grace = Duration::from_millis(300);
+        let forced = terminate_with("test", pid, grace, || running.load(Ordering::SeqCst)).await;
+        assert!(forced);
+        assert!(started.elapsed() >= grace);
+        assert!(!running.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,221 @@
+use serde_json::Value;
+
+/// The template used before templates became configurable.
+pub const DEFAULT_TEMPLATE: &str = "%(title)s [%(id)s].%(ext)s";
+
+/// One `%(...)X` placeholder of a yt-dlp output template.
+struct Field<'a> {
+    /// Comma-separated alternatives, each a dotted path into the info JSON.
+    names: Vec<&'a str>,
+    /// `>` date format applied to `YYYYMMDD` values such as `upload_date`.
+    date_format: Option<&'a str>,
+    /// `|` fallback when no alternative has a value.
+    default: Option<&'a str>,
+    /// Flags, width and precision between `)` and the conversion, e.g. `03` in `%(playlist_index)03d`.
+    spec: &'a str,
+    conversion: char,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Field(Field<'a>),
+}
+
+/// Python's `%` conversions plus the ones yt-dlp adds (`B`, `j`, `h`, `l`, `q`, `D`, `S`, `U`).
+const CONVERSIONS: &str = "diouxXeEfFgGcrsaBjhlqDSU";
+
+fn parse(template: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find('%') {
+        if pos > 0 {
+            tokens.push(Token::Text(&rest[..pos]));
+        }
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('%') {
+            tokens.push(Token::Text("%"));
+            rest = after;
+            continue;
+        }
+        let inner = rest.strip_prefix('(').ok_or("A '%' must be followed by '(field)' or escaped as '%%'")?;
+        let close = inner.find(')').ok_or("Unclosed '%(' in output template")?;
+        let (body, after) = (&inner[..close], &inner[close + 1..]);
+
+        let spec_len = after
+            .find(|c: char| !(c.is_ascii_digit() || "-+ #.".contains(c)))
+            .ok_or("Output template field is missing a conversion type (e.g. 's')")?;
+        let conversion = after[spec_len..].chars().next().unwrap_or(' ');
+        if !CONVERSIONS.contains(conversion) {
+            return Err(format!("Unsupported conversion '%(...){}' in output template", conversion));
+        }
+
+        let (body, default) = match body.split_once('|') {
+            Some((body, default)) => (body, Some(default)),
+            None => (body, None),
+        };
+        let (body, date_format) = match body.split_once('>') {
+            Some((body, format)) => (body, Some(format)),
+            None => (body, None),
+        };
+        let names: Vec<&str> = body.split(',').map(str::trim).collect();
+        if names.iter().any(|n| n.is_empty()) {
+            return Err("Output template contains an empty field name".into());
+        }
+
+        tokens.push(Token::Field(Field { names, date_format, default, spec: &after[..spec_len], conversion }));
+        rest = &after[spec_len + conversion.len_utf8()..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    Ok(tokens)
+}
+
+/// Checks that a user template is something yt-dlp accepts and that it stays inside
+/// the chosen output folder.
+pub fn validate(template: &str) -> Result<(), String> {
+    let template = template.trim();
+    if template.is_empty() {
+        return Err("Output template cannot be empty".into());
+    }
+    if template.starts_with('/') || template.starts_with('\\') || template.chars().nth(1) == Some(':') {
+        return Err("Output template must be relative to the download folder".into());
+    }
+    if template.split(['/', '\\']).any(|part| part.trim() == "..") {
+        return Err("Output template cannot point outside the download folder ('..')".into());
+    }
+    let tokens = parse(template)?;
+    let has_ext = tokens.iter().any(|t| matches!(t, Token::Field(f) if f.names.contains(&"ext")));
+    if !has_ext {
+        return Err("Output template must include %(ext)s".into());
+    }
+    Ok(())
+}
+
+fn lookup<'v>(info: &'v Value, path: &str) -> Option<&'v Value> {
+    let mut value = info;
+    for key in path.split('.') {
+        value = match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => value.get(key)?,
+        };
+    }
+    (!value.is_null()).then_some(value)
+}
+
+/// Same substitutions yt-dlp makes for field values in filenames (non-restricted mode).
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '/' => '\u{29F8}',
+            '\\' => '\u{29F9}',
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => char::from_u32(c as u32 + 0xFEE0).unwrap_or('_'),
+            _ => c,
+        })
+        .collect()
+}
+
+fn format_date(value: &str, format: &str) -> Option<String> {
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some(date.format(format).to_string())
+}
+
+fn render_field(field: &Field, info: &Value) -> String {
+    let value = field.names.iter().find_map(|name| lookup(info, name));
+    let Some(value) = value else {
+        return field.default.unwrap_or("NA").to_string();
+    };
+
+    let text = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if let Some(format) = field.date_format {
+        if let Some(date) = format_date(&text, format) {
+            return sanitize(&date);
+        }
+    }
+
+    let zero_pad = field.spec.starts_with('0');
+    let (width, precision) = match field.spec.trim_start_matches(['-', '+', ' ', '#', '0']).split_once('.') {
+        Some((w, p)) => (w.parse().unwrap_or(0), p.parse::<usize>().ok()),
+        None => (field.spec.trim_start_matches(['-', '+', ' ', '#', '0']).parse().unwrap_or(0), None),
+    };
+
+    let formatted = match field.conversion {
+        'd' | 'i' => match value.as_f64() {
+            Some(n) if zero_pad => format!("{:0width$}", n as i64, width = width),
+            Some(n) => format!("{:width$}", n as i64, width = width),
+            None => text,
+        },
+        'f' => match value.as_f64() {
+            Some(n) => format!("{:width$.prec$}", n, width = width, prec = precision.unwrap_or(6)),
+            None => text,
+        },
+        'j' => value.to_string(),
+        _ => {
+            let text: String = match precision {
+                Some(p) => text.chars().take(p).collect(),
+                None => text,
+            };
+            format!("{:width$}", text, width = width)
+        }
+    };
+    sanitize(&formatted)
+}
+
+/// Renders `template` against the `-J` JSON of a video, approximating the filename
+/// yt-dlp will produce. Missing fields become `NA`, as in yt-dlp.
+pub fn render(template: &str, info: &Value) -> Result<String, String> {
+    let mut out = String::new();
+    for token in parse(template)? {
+        match token {
+            Token::Text(text) => out.push_str(text),
+            Token::Field(field) => out.push_str(&render_field(&field, info)),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_yt_dlp_templates() {
+        for template in [
+            DEFAULT_TEMPLATE,
+            "%(title).50U [%(id)s].%(ext)s",
+            "%(title)+.100U.%(ext)s",
+            "%(title)#S.%(ext)s",
+            "%(uploader)h - %(title)c.%(ext)s",
+            "%(playlist_index)03d - %(title)s.%(ext)s",
+            "%(upload_date>%Y-%m-%d)s %(title)s.%(ext)s",
+            "%(channel,uploader|Unknown)s/%(title)s.%(ext)s",
+            "%(view_count)x %(duration)5.1f 100%% %(title)s.%(ext)s",
+        ] {
+            assert_eq!(validate(template), Ok(()), "{}", template);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in [
+            "",
+            "   ",
+            "/tmp/%(title)s.%(ext)s",
+            "C:\\videos\\%(title)s.%(ext)s",
+            "../%(title)s.%(ext)s",
+            "%(title)s",
+            "%(title)s.%(ext)",
+            "%(title)z.%(ext)s",
+            "%(title.%(ext)s",
+            "100% %(title)s.%(ext)s",
+            "%(,)s.%(ext)s",
+        ] {
+            assert!(validate(template).is_err(), "{:?}", template);
+        }
+    }
+}
@@ -0,0 +1,546 @@
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Timeouts, retries and progress pacing for `download`.
+#[derive(Clone, Debug)]
+pub struct TransferOptions {
+    /// Longest wait for the response headers of one request.
+    pub response_timeout: Duration,
+    /// Longest gap between two received chunks before the attempt is abandoned.
+    pub idle_timeout: Duration,
+    /// Total requests tried, including the first.
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for every further one.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Minimum time between two progress callbacks.
+    pub progress_interval: Duration,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            response_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(30),
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            progress_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// The user-adjustable part of `TransferOptions`, kept in settings.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransferSettings {
+    pub response_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub max_attempts: u32,
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        let options = TransferOptions::default();
+        TransferSettings {
+            response_timeout_secs: options.response_timeout.as_secs(),
+            idle_timeout_secs: options.idle_timeout.as_secs(),
+            max_attempts: options.max_attempts,
+        }
+    }
+}
+
+impl TransferSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=600).contains(&self.response_timeout_secs) || !(1..=600).contains(&self.idle_timeout_secs) {
+            return Err("Download timeouts must be between 1 and 600 seconds".into());
+        }
+        if !(1..=20).contains(&self.max_attempts) {
+            return Err("Download attempts must be between 1 and 20".into());
+        }
+        Ok(())
+    }
+
+    pub fn options(&self) -> TransferOptions {
+        TransferOptions {
+            response_timeout: Duration::from_secs(self.response_timeout_secs),
+            idle_timeout: Duration::from_secs(self.idle_timeout_secs),
+            max_attempts: self.max_attempts,
+            ..TransferOptions::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TransferProgress {
+    pub downloaded: u64,
+    /// Unknown when the server sends no length.
+    pub total: Option<u64>,
+}
+
+impl TransferProgress {
+    pub fn percent(&self) -> Option<f64> {
+        self.total.filter(|t| *t > 0).map(|t| self.downloaded as f64 / t as f64 * 100.0)
+    }
+}
+
+/// How one request ended when it did not complete the file.
+enum AttemptError {
+    /// Worth another request (network errors, timeouts, 5xx, 408, 429).
+    Transient(String),
+    Fatal(String),
+}
+
+/// Sidecar holding the ETag/Last-Modified of a partial download, so a resume can ask the
+/// server (with `If-Range`) to only continue it if the file has not changed.
+fn validator_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".validator");
+    dest.with_file_name(name)
+}
+
+fn validator(headers: &HeaderMap) -> Option<String> {
+    // Weak ETags may not be used with If-Range.
+    let etag = headers.get(ETAG).and_then(|v| v.to_str().ok()).filter(|e| !e.starts_with("W/"));
+    etag.or_else(|| headers.get(LAST_MODIFIED).and_then(|v| v.to_str().ok())).map(String::from)
+}
+
+/// Parses `Content-Range: bytes <start>-<end>/<total>` (or `bytes */<total>` on a 416).
+fn content_range(headers: &HeaderMap) -> Option<(Option<u64>, Option<u64>)> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-').and_then(|(start, _)| start.parse().ok());
+    Some((start, total.parse().ok()))
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Bytes of a partial download that can be continued. A file from an earlier call is
+/// only trusted with a recorded validator, since it may belong to another version.
+fn resumable_length(dest: &Path, same_call: bool) -> (u64, Option<String>) {
+    let validator = std::fs::read_to_string(validator_path(dest)).ok().filter(|v| !v.is_empty());
+    match std::fs::metadata(dest) {
+        Ok(meta) if meta.len() > 0 && (same_call || validator.is_some()) => (meta.len(), validator),
+        _ => (0, None),
+    }
+}
+
+async fn attempt<F>(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    options: &TransferOptions,
+    on_progress: &mut F,
+    last_report: &mut Option<Instant>,
+    same_call: bool,
+) -> Result<u64, AttemptError>
+where
+    F: FnMut(TransferProgress),
+{
+    let (offset, known_validator) = resumable_length(dest, same_call);
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = &known_validator {
+            request = request.header(IF_RANGE, validator.as_str());
+        }
+    }
+
+    let response = tokio::time::timeout(options.response_timeout, request.send())
+        .await
+        .map_err(|_| AttemptError::Transient("Timed out waiting for the server".into()))?
+        .map_err(|e| AttemptError::Transient(e.to_string()))?;
+    let status = response.status();
+
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+        // Usually the previous attempt already got everything.
+        if content_range(response.headers()).and_then(|(_, total)| total) == Some(offset) {
+            let _ = std::fs::remove_file(validator_path(dest));
+            on_progress(TransferProgress { downloaded: offset, total: Some(offset) });
+            return Ok(offset);
+        }
+        let _ = std::fs::remove_file(validator_path(dest));
+        let _ = std::fs::remove_file(dest);
+        return Err(AttemptError::Transient("Server rejected the resume range, restarting".into()));
+    }
+    if !status.is_success() {
+        let message = format!("Server responded with {} for {}", status, url);
+        return Err(if is_transient(status) { AttemptError::Transient(message) } else { AttemptError::Fatal(message) });
+    }
+
+    // 206 continues the partial file; a 200 means the server sent the whole file again.
+    let resumed = status == StatusCode::PARTIAL_CONTENT
+        && offset > 0
+        && content_range(response.headers()).and_then(|(start, _)| start) == Some(offset);
+    let start = if resumed { offset } else { 0 };
+    let total = match content_range(response.headers()) {
+        Some((_, Some(total))) if resumed => Some(total),
+        _ => response.content_length().map(|len| len + start),
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(dest)
+        .map_err(|e| AttemptError::Fatal(format!("Failed to open {:?}: {}", dest, e)))?;
+    match validator(response.headers()) {
+        Some(validator) => {
+            let _ = std::fs::write(validator_path(dest), validator);
+        }
+        None => {
+            let _ = std::fs::remove_file(validator_path(dest));
+        }
+    }
+
+    let mut downloaded = start;
+    let mut stream = response.bytes_stream();
+    loop {
+        let chunk = match tokio::time::timeout(options.idle_timeout, stream.next()).await {
+            Err(_) => return Err(AttemptError::Transient(format!("No data received for {:?}", options.idle_timeout))),
+            Ok(None) => break,
+            Ok(Some(Err(e))) => return Err(AttemptError::Transient(e.to_string())),
+            Ok(Some(Ok(chunk))) => chunk,
+        };
+        file.write_all(&chunk).map_err(|e| AttemptError::Fatal(format!("Failed to write {:?}: {}", dest, e)))?;
+        downloaded += chunk.len() as u64;
+        if last_report.is_none_or(|at| at.elapsed() >= options.progress_interval) {
+            *last_report = Some(Instant::now());
+            on_progress(TransferProgress { downloaded, total });
+        }
+    }
+    file.flush().map_err(|e| AttemptError::Fatal(e.to_string()))?;
+
+    if total.is_some_and(|total| downloaded < total) {
+        return Err(AttemptError::Transient(format!("Connection closed after {} of {:?} bytes", downloaded, total)));
+    }
+    let _ = std::fs::remove_file(validator_path(dest));
+    on_progress(TransferProgress { downloaded, total });
+    Ok(downloaded)
+}
+
+/// Downloads `url` into `dest`, continuing a partial file left by an earlier attempt
+/// (in this call or a previous one) with HTTP Range requests, and retrying transient
+/// failures with exponential backoff. Progress is reported at most every
+/// `progress_interval`, plus once at the end. Returns the file size.
+pub async fn download<F>(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    options: &TransferOptions,
+    mut on_progress: F,
+) -> Result<u64, String>
+where
+    F: FnMut(TransferProgress),
+{
+    let mut last_report = None;
+    let mut backoff = options.initial_backoff;
+    let mut attempt_no = 1;
+    loop {
+        match attempt(client, url, dest, options, &mut on_progress, &mut last_report, attempt_no > 1).await {
+            Ok(size) => return Ok(size),
+            Err(AttemptError::Fatal(e)) => return Err(e),
+            Err(AttemptError::Transient(e)) if attempt_no >= options.max_attempts => {
+                return Err(format!("{} (gave up after {} attempts)", e, attempt_no));
+            }
+            Err(AttemptError::Transient(e)) => {
+                println!("[Transfer] {} failed ({}), retrying in {:?}", url, e, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(options.max_backoff);
+                attempt_no += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    const ETAG_V1: &str = "\"v1\"";
+
+    fn body() -> Vec<u8> {
+        (0..1000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// What the stand-in server sends for one request.
+    struct Reply {
+        status: &'static str,
+        headers: Vec<String>,
+        body: Vec<u8>,
+        /// Bytes of the body sent before the connection is dropped.
+        cut: Option<usize>,
+        /// Bytes of the body sent before the connection goes silent.
+        stall: Option<usize>,
+        /// Sends the body in pieces of this size, pausing in between.
+        trickle: Option<(usize, Duration)>,
+    }
+
+    fn reply(status: &'static str, body: &[u8]) -> Reply {
+        Reply { status, headers: Vec::new(), body: body.to_vec(), cut: None, stall: None, trickle: None }
+    }
+
+    impl Reply {
+        fn header(mut self, header: &str) -> Self {
+            self.headers.push(header.to_string());
+            self
+        }
+
+        async fn write(self, mut socket: TcpStream) {
+            let mut head = format!("HTTP/1.1 {}\r\nConnection: close\r\nContent-Length: {}\r\n", self.status, self.body.len());
+            for header in &self.headers {
+                head.push_str(&format!("{}\r\n", header));
+            }
+            head.push_str("\r\n");
+            let _ = socket.write_all(head.as_bytes()).await;
+            let sent = self.cut.or(self.stall).unwrap_or(self.body.len());
+            match self.trickle {
+                Some((size, pause)) => {
+                    for piece in self.body[..sent].chunks(size) {
+                        let _ = socket.write_all(piece).await;
+                        tokio::time::sleep(pause).await;
+                    }
+                }
+                None => {
+                    let _ = socket.write_all(&self.body[..sent]).await;
+                }
+            }
+            if self.stall.is_some() {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+            let _ = socket.shutdown().await;
+        }
+    }
+
+    /// Serves every request with `handler(request_no, request_head)` and records the
+    /// lowercased request heads.
+    async fn server<F>(mut handler: F) -> (String, Arc<Mutex<Vec<String>>>)
+    where
+        F: FnMut(usize, &str) -> Reply + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/file.bin", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            for n in 0.. {
+                let Ok((mut socket, _)) = listener.accept().await else { break };
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => head.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head).to_lowercase();
+                seen.lock().unwrap().push(head.clone());
+                tokio::spawn(handler(n, &head).write(socket));
+            }
+        });
+        (url, requests)
+    }
+
+    fn options() -> TransferOptions {
+        TransferOptions {
+            response_timeout: Duration::from_secs(5),
+            idle_timeout: Duration::from_secs(5),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(10),
+            progress_interval: Duration::from_millis(250),
+        }
+    }
+
+    /// A path in the temp folder whose file and validator are removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl std::ops::Deref for TempFile {
+        type Target = Path;
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempFile {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+            let _ = std::fs::remove_file(validator_path(&self.0));
+        }
+    }
+
+    fn dest(name: &str) -> TempFile {
+        let name = format!("hmd-transfer-{}-{}", std::process::id(), name);
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(validator_path(&path));
+        TempFile(path)
+    }
+
+    async fn fetch(url: &str, path: &Path, options: &TransferOptions) -> (Result<u64, String>, Vec<TransferProgress>) {
+        let mut reports = Vec::new();
+        let result = download(&reqwest::Client::new(), url, path, options, |p| reports.push(p)).await;
+        (result, reports)
+    }
+
+    #[tokio::test]
+    async fn resumes_with_a_range_after_a_dropped_connection() {
+        let full = body();
+        let served = full.clone();
+        let (url, requests) = server(move |n, _| match n {
+            0 => Reply { cut: Some(400), ..reply("200 OK", &served).header(&format!("ETag: {}", ETAG_V1)) },
+            _ => reply("206 Partial Content", &served[400..])
+                .header("Content-Range: bytes 400-999/1000")
+                .header(&format!("ETag: {}", ETAG_V1)),
+        })
+        .await;
+        let path = dest("resume");
+
+        let (result, _) = fetch(&url, &path, &options()).await;
+        assert_eq!(result, Ok(1000));
+        assert_eq!(std::fs::read(&path).unwrap(), full);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("range: bytes=400-"), "{}", requests[1]);
+        assert!(requests[1].contains("if-range: \"v1\""), "{}", requests[1]);
+        assert!(!validator_path(&path).exists());
+    }
+
+    #[tokio::test]
+    async fn restarts_when_the_server_ignores_the_range() {
+        let full = body();
+        let served = full.clone();
+        let (url, requests) = server(move |n, _| match n {
+            0 => Reply { cut: Some(400), ..reply("200 OK", &served) },
+            _ => reply("200 OK", &served),
+        })
+        .await;
+        let path = dest("ignored-range");
+
+        let (result, _) = fetch(&url, &path, &options()).await;
+        assert_eq!(result, Ok(1000));
+        assert_eq!(std::fs::read(&path).unwrap(), full);
+        assert!(requests.lock().unwrap()[1].contains("range: bytes=400-"));
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_file_changed_since_the_partial_download() {
+        let full = body();
+        let served = full.clone();
+        // Like a real server: the range only applies while the validator still matches.
+        let (url, requests) = server(move |_, head| {
+            if head.contains("if-range: \"v2\"") {
+                reply("206 Partial Content", &served[400..]).header("Content-Range: bytes 400-999/1000")
+            } else {
+                reply("200 OK", &served).header("ETag: \"v2\"")
+            }
+        })
+        .await;
+        let path = dest("changed");
+        std::fs::write(&path, vec![b'x'; 400]).unwrap();
+        std::fs::write(validator_path(&path), ETAG_V1).unwrap();
+
+        let (result, _) = fetch(&url, &path, &options()).await;
+        assert_eq!(result, Ok(1000));
+        assert_eq!(std::fs::read(&path).unwrap(), full);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("if-range: \"v1\""), "{}", requests[0]);
+    }
+
+    #[tokio::test]
+    async fn treats_416_on_a_complete_file_as_done() {
+        let (url, requests) = server(|_, _| reply("416 Range Not Satisfiable", b"").header("Content-Range: bytes */1000")).await;
+        let path = dest("complete");
+        std::fs::write(&path, body()).unwrap();
+        std::fs::write(validator_path(&path), ETAG_V1).unwrap();
+
+        let (result, reports) = fetch(&url, &path, &options()).await;
+        assert_eq!(result, Ok(1000));
+        assert_eq!(std::fs::read(&path).unwrap(), body());
+        assert!(!validator_path(&path).exists());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(reports.last().and_then(|p| p.percent()), Some(100.0));
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_rate_limits() {
+        let full = body();
+        let served = full.clone();
+        let (url, requests) = server(move |n, _| match n {
+            0 => reply("503 Service Unavailable", b""),
+            1 => reply("429 Too Many Requests", b""),
+            _ => reply("200 OK", &served),
+        })
+        .await;
+        let path = dest("retry");
+
+        let (result, _) = fetch(&url, &path, &options()).await;
+        assert_eq!(result, Ok(1000));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(std::fs::read(&path).unwrap(), full);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_not_found_right_away() {
+        let (url, requests) = server(|_, _| reply("404 Not Found", b"")).await;
+        let path = dest("missing");
+
+        let (result, _) = fetch(&url, &path, &options()).await;
+        assert!(result.unwrap_err().contains("404"));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn abandons_a_stalled_body_after_the_idle_timeout() {
+        let full = body();
+        let served = full.clone();
+        let (url, requests) = server(move |n, _| match n {
+            0 => Reply { stall: Some(100), ..reply("200 OK", &served) },
+            _ => reply("206 Partial Content", &served[100..]).header("Content-Range: bytes 100-999/1000"),
+        })
+        .await;
+        let path = dest("stalled");
+        let options = TransferOptions { idle_timeout: Duration::from_millis(300), ..options() };
+
+        let started = Instant::now();
+        let (result, _) = fetch(&url, &path, &options).await;
+        assert_eq!(result, Ok(1000));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(std::fs::read(&path).unwrap(), full);
+        assert!(requests.lock().unwrap()[1].contains("range: bytes=100-"));
+    }
+
+    #[tokio::test]
+    async fn throttles_progress_reports() {
+        let served = body();
+        // 20 pieces 25 ms apart: about half a second of transfer.
+        let (url, _) = server(move |_, _| Reply { trickle: Some((50, Duration::from_millis(25))), ..reply("200 OK", &served) }).await;
+        let path = dest("progress");
+        let options = TransferOptions { progress_interval: Duration::from_millis(200), ..options() };
+
+        let (result, reports) = fetch(&url, &path, &options).await;
+        assert_eq!(result, Ok(1000));
+        assert!((2..=6).contains(&reports.len()), "{} reports", reports.len());
+        assert!(reports.windows(2).all(|w| w[0].downloaded <= w[1].downloaded));
+        let last = reports.last().unwrap();
+        assert_eq!((last.downloaded, last.total), (1000, Some(1000)));
+    }
+}
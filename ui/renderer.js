@@ -559,7 +559,7 @@ document.addEventListener("DOMContentLoaded", () => {
     if (path) pathInput.value = path;
   });
 
-  confirmBtn.addEventListener("click", () => {
+  confirmBtn.addEventListener("click", async () => {
     if (!pathInput.value) {
       alert("Please select a destination folder.");
       return;
@@ -579,8 +579,27 @@ document.addEventListener("DOMContentLoaded", () => {
       const finalPath = `${basePath}/${subfolder}`;
 
       const groupId = `group_${Date.now()}`;
+      const selected = [...selectedPlaylistIndices].sort((a, b) => a - b);
       const groupDownload = {
         id: groupId,
+        // The whole selection runs as one backend job; child rows only display progress.
+        args: {
+          id: groupId,
+          url: currentConfig.url,
+          type: currentConfig.type,
+          quality: currentConfig.quality,
+          outputDir: basePath,
+          videoExt: videoExtSelect.value,
+          audioExt: audioExtSelect.value,
+          thumbExt: thumbExtSelect.value,
+          hwAccel: hwAccelSelect.value,
+          title: playlistTitle,
+          playlist: true,
+          playlistItems:
+            selected.length === playlistEntries.length
+              ? null
+              : selected.map((idx) => idx + 1).join(","),
+        },
         title: playlistTitle,
         thumb: modalThumb.src,
         type: "playlist",
@@ -595,21 +614,52 @@ document.addEventListener("DOMContentLoaded", () => {
       downloads[groupId] = groupDownload;
       addDownloadRow(groupDownload);
 
-      selectedPlaylistIndices.forEach((idx) => {
+      selected.forEach((idx) => {
         const entry = playlistEntries[idx];
         const childId = `${groupId}_${idx}`;
-        const config = {
-          ...currentConfig,
-          url: entry.url || currentConfig.url,
-          info: entry,
+        const child = {
+          id: childId,
+          videoId: entry.id,
+          title: entry.title,
+          thumb: getBestThumbnail(entry),
+          type: currentConfig.type,
+          qualityName: groupDownload.qualityName,
           path: finalPath,
-          playlistIndex: idx + 1,
+          progress: 0,
+          status: "QUEUED",
+          parentId: groupId,
+          element: null,
         };
-
-        const childDownload = startDownload(null, config, childId, groupId);
+        downloads[childId] = child;
+        addDownloadRow(child);
         groupDownload.children.push(childId);
       });
+
+      startDownload(groupId);
     } else {
+      currentConfig.overwrite = false;
+      const duplicate = await window.electronAPI
+        .invoke("check-duplicate", {
+          url: currentConfig.url,
+          extractor: currentConfig.info.extractor_key,
+          videoId: currentConfig.info.id,
+          outputDir: currentConfig.path,
+        })
+        .catch(() => null);
+      if (duplicate && duplicate.duplicate) {
+        const again = await showConfirm(
+          "ALREADY DOWNLOADED",
+          duplicate.message,
+          "DOWNLOAD AGAIN",
+          "SKIP",
+        );
+        if (!again) {
+          modal.classList.add("hidden");
+          urlInput.value = "";
+          return;
+        }
+        currentConfig.overwrite = true;
+      }
       startDownload();
     }
 
@@ -617,14 +667,9 @@ document.addEventListener("DOMContentLoaded", () => {
     urlInput.value = "";
   });
 
-  function startDownload(
-    resumeId = null,
-    playlistConfig = null,
-    customId = null,
-    parentId = null,
-  ) {
+  function startDownload(resumeId = null) {
     let id, args, download;
-    let config = playlistConfig || currentConfig;
+    const config = currentConfig;
 
     if (resumeId) {
       id = resumeId;
@@ -633,10 +678,7 @@ document.addEventListener("DOMContentLoaded", () => {
       download.status = "INITIALIZING";
       updateDownloadUI(id);
     } else {
-      id =
-        customId ||
-        Date.now().toString() +
-          (playlistConfig ? `_${playlistConfig.playlistIndex}` : "");
+      id = Date.now().toString();
 
       args = {
         id: id,
@@ -648,6 +690,8 @@ document.addEventListener("DOMContentLoaded", () => {
         audioExt: audioExtSelect.value,
         thumbExt: thumbExtSelect.value,
         hwAccel: hwAccelSelect.value,
+        title: config.info.title,
+        overwrite: !!config.overwrite,
       };
 
       download = {
@@ -663,7 +707,6 @@ document.addEventListener("DOMContentLoaded", () => {
         path: config.path,
         progress: 0,
         status: "INITIALIZING",
-        parentId,
         element: null,
       };
       downloads[id] = download;
@@ -674,6 +717,9 @@ document.addEventListener("DOMContentLoaded", () => {
       console.error(`Download ${id} failed to start:`, err);
       download.status = "ERROR";
       updateDownloadUI(id);
+      if (err && err.code === "insufficient_space") {
+        showAlert("NOT ENOUGH DISK SPACE", err.message);
+      }
     });
     return download;
   }
@@ -807,6 +853,8 @@ document.addEventListener("DOMContentLoaded", () => {
         parent.status = "COMPLETED";
       else if (children.every((c) => c.status === "PAUSED"))
         parent.status = "PAUSED";
+      else if (children.every((c) => ["COMPLETED", "ERROR"].includes(c.status)))
+        parent.status = children.some((c) => c.status === "COMPLETED") ? "COMPLETED" : "ERROR";
       else parent.status = "QUEUED";
 
       updateDownloadUI(parentId);
@@ -816,15 +864,20 @@ document.addEventListener("DOMContentLoaded", () => {
   function togglePause(id) {
     const dl = downloads[id];
     if (!dl) return;
+    // Playlist entries share their group's process.
+    if (dl.parentId) return togglePause(dl.parentId);
 
-    if (dl.isGroup) {
-      const isPaused = dl.status === "PAUSED";
-      dl.children.forEach((childId) => togglePause(childId));
-      updatePauseIcon(id, !isPaused);
-      return;
+    if (dl.isGroup && dl.status !== "PAUSED") {
+      dl.children.forEach((childId) => {
+        const child = downloads[childId];
+        if (child && ["DOWNLOADING", "INITIALIZING", "FINALIZING"].includes(child.status)) {
+          child.status = "PAUSED";
+          updateDownloadUI(childId);
+        }
+      });
     }
 
-    const activeStates = ["DOWNLOADING", "INITIALIZING", "FINALIZING"];
+    const activeStates = ["DOWNLOADING", "INITIALIZING", "FINALIZING", "RETRYING"];
     const resumableStates = ["PAUSED", "ERROR", "CANCELLED"];
 
     if (activeStates.includes(dl.status)) {
@@ -834,14 +887,23 @@ document.addEventListener("DOMContentLoaded", () => {
       updateDownloadUI(id);
       updatePauseIcon(id, true);
       updateGlobalStats();
-      window.electronAPI.invoke("cancel-download", id);
+      window.electronAPI.invoke("pause-download", id).catch((err) => {
+        console.error(`Failed to pause ${id}:`, err);
+        window.electronAPI.invoke("cancel-download", id);
+      });
     } else if (resumableStates.includes(dl.status)) {
+      const wasPaused = dl.status === "PAUSED";
       dl.isPausing = false;
       dl.isCancelling = false;
       dl.status = "RESUMING";
       updateDownloadUI(id);
       updatePauseIcon(id, false);
-      startDownload(id);
+      if (wasPaused) {
+        // The backend still has the paused job; fall back to a fresh start if not.
+        window.electronAPI.invoke("resume-download", id).catch(() => startDownload(id));
+      } else {
+        startDownload(id);
+      }
     }
   }
 
@@ -892,6 +954,12 @@ document.addEventListener("DOMContentLoaded", () => {
     else tag.style.color = "var(--accent-primary)";
   }
 
+  function outputFiles(dl) {
+    const files = new Set(dl.files || []);
+    if (dl.finalPath) files.add(dl.finalPath);
+    return [...files];
+  }
+
   function removeDownload(id) {
     const dl = downloads[id];
     if (!dl) return;
@@ -901,9 +969,7 @@ document.addEventListener("DOMContentLoaded", () => {
       dl.children.forEach(childId => {
         const child = downloads[childId];
         if (child) {
-          if (child.finalPath) {
-             window.electronAPI.invoke("delete_file", child.finalPath);
-          }
+          outputFiles(child).forEach((file) => window.electronAPI.invoke("delete_file", file));
           if (child.element) {
             child.element.remove();
           }
@@ -912,9 +978,7 @@ document.addEventListener("DOMContentLoaded", () => {
       });
     } else {
       // Delete physical file for single items
-      if (dl.finalPath) {
-        window.electronAPI.invoke("delete_file", dl.finalPath);
-      }
+      outputFiles(dl).forEach((file) => window.electronAPI.invoke("delete_file", file));
     }
 
     if (dl.element) {
@@ -927,80 +991,136 @@ document.addEventListener("DOMContentLoaded", () => {
     updateGlobalStats();
   }
 
-  window.electronAPI.on("ytdlp-output", (payload) => {
-    const { id, data } = payload;
-    const dl = downloads[id];
-    if (!dl || !dl.element || dl.isPausing || dl.isCancelling) return;
-    if (dl.status === "PAUSED" || dl.status === "CANCELLED") return;
+  function formatEta(seconds) {
+    if (seconds == null) return "---";
+    const h = Math.floor(seconds / 3600);
+    const m = Math.floor((seconds % 3600) / 60);
+    if (h > 0) return `${h}h ${m}m`;
+    if (m > 0) return `${m}m`;
+    return `${seconds}s`;
+  }
 
-    const row = dl.element;
-    const lines = data.split(/[\r\n]+/);
+  window.electronAPI.on("download-progress", (progress) => {
+    const dl = downloads[progress.id];
+    if (!dl || dl.isPausing || dl.isCancelling) return;
+    if (dl.status === "PAUSED" || dl.status === "CANCELLED") return;
 
-    for (const line of lines) {
-      const trimmed = line.trim();
-      if (!trimmed) continue;
+    // Playlist jobs report progress per entry; show it on the entry's child row.
+    if (dl.isGroup && progress.playlist_index != null) {
+      applyProgress(`${dl.id}_${progress.playlist_index - 1}`, progress);
+      return;
+    }
+    applyProgress(progress.id, progress);
+  });
 
-      const percentMatch = trimmed.match(
-        /\[download\]\s+([\d.]+)%\s+of\s+(?:~?)\s*(.+?)(?:\s+at\s+(.+?)\s+ETA\s+(.+?))?$/i,
-      );
-      if (percentMatch) {
-        const [, percent, size, speed, eta] = percentMatch;
-        const fill = row.querySelector(".progress-bar-fill");
-        const pText = row.querySelector(".percent");
-        const sText = row.querySelector(".speed");
-
-        fill.style.width = `${percent}%`;
-        pText.textContent = `${percent}%`;
-        
-        let formattedEta = "---";
-        if (eta) {
-          const parts = eta.split(":");
-          if (parts.length === 3) { // HH:MM:SS
-            const h = parseInt(parts[0]);
-            const m = parseInt(parts[1]);
-            formattedEta = h > 0 ? `${h}h ${m}m` : `${m}m`;
-          } else if (parts.length === 2) { // MM:SS
-            formattedEta = `${parseInt(parts[0])}m`;
-          } else {
-            formattedEta = eta;
-          }
-        }
+  function applyProgress(id, progress) {
+    const dl = downloads[id];
+    if (!dl || !dl.element) return;
 
-        sText.textContent = speed ? `${speed} // ETA: ${formattedEta}` : "---";
+    const row = dl.element;
 
+    if (progress.phase === "downloading" || progress.phase === "downloaded") {
+      if (progress.percent != null) {
+        const percent = progress.percent.toFixed(1);
+        row.querySelector(".progress-bar-fill").style.width = `${percent}%`;
+        row.querySelector(".percent").textContent = `${percent}%`;
         dl.progress = parseFloat(percent);
         if (dl.parentId) updateParentProgress(dl.parentId);
+      }
 
-        if (speed) {
-          dl.lastSpeed = speed;
-          dl.lastSpeedValue = parseSpeed(speed);
-        }
+      const sText = row.querySelector(".speed");
+      if (progress.speed) {
+        sText.textContent = `${formatSpeed(progress.speed)} // ETA: ${formatEta(progress.eta)}`;
+        dl.lastSpeedValue = progress.speed;
+      } else {
+        sText.textContent = "---";
+      }
 
-        if (dl.status !== "DOWNLOADING") {
-          dl.status = "DOWNLOADING";
-          updateDownloadUI(id);
-        }
-        updateGlobalStats();
-        continue;
+      if (dl.status !== "DOWNLOADING") {
+        dl.status = "DOWNLOADING";
+        updateDownloadUI(id);
       }
+      updateGlobalStats();
+      return;
+    }
 
-      if (trimmed.includes("[Merger]") || trimmed.includes("[ExtractAudio]")) {
-        if (dl.status !== "FINALIZING") {
-          dl.status = "FINALIZING";
-          updateDownloadUI(id);
-        }
+    if (dl.status !== "FINALIZING") {
+      dl.status = "FINALIZING";
+      dl.lastSpeedValue = 0;
+      row.querySelector(".speed").textContent = progress.phase.replace(/_/g, " ").toUpperCase();
+      updateDownloadUI(id);
+      updateGlobalStats();
+    }
+  }
+
+  window.electronAPI.on("playlist-entry-finished", ({ id, index, path }) => {
+    const child = downloads[`${id}_${index - 1}`];
+    if (!child || !child.element) return;
+    child.finalPath = path;
+    child.progress = 100;
+    child.status = "COMPLETED";
+    child.element.querySelector(".progress-bar-fill").style.width = "100%";
+    child.element.querySelector(".percent").textContent = "100%";
+    child.element.querySelector(".speed").textContent = "---";
+    updateDownloadUI(child.id);
+    updateParentProgress(id);
+  });
+
+  window.electronAPI.on("playlist-entry-error", ({ id, index, video_id, error }) => {
+    const group = downloads[id];
+    if (!group || !group.isGroup) return;
+    const childId = group.children.find((cid) => {
+      const child = downloads[cid];
+      if (!child) return false;
+      if (index != null) return cid === `${id}_${index - 1}`;
+      return video_id != null && child.videoId === video_id;
+    });
+    const child = downloads[childId];
+    if (!child) return;
+    child.status = "ERROR";
+    child.error = error;
+    if (child.element) child.element.title = error;
+    updateDownloadUI(childId);
+    updateParentProgress(id);
+  });
+
+  window.electronAPI.on("queue-updated", (snapshot) => {
+    for (const job of snapshot.jobs) {
+      const dl = downloads[job.id];
+      if (!dl || !dl.element || dl.isPausing || dl.isCancelling) continue;
+      if (job.status === "pending" && dl.status !== "QUEUED") {
+        dl.status = "QUEUED";
+        updateDownloadUI(job.id);
+      } else if (job.status === "active" && dl.status === "QUEUED") {
+        dl.status = "INITIALIZING";
+        updateDownloadUI(job.id);
       }
     }
   });
 
   window.electronAPI.on("download-finished", (payload) => {
-    const { id, code, path } = payload;
+    const { id, code, path, files } = payload;
     const dl = downloads[id];
     if (!dl || !dl.element) return;
 
     if (path) dl.finalPath = path;
+    if (files) dl.files = files;
 
     if (dl.isPausing || dl.isCancelling) return;
+    if (dl.isGroup) {
+      // Entries that produced no file event were already present (or archived).
+      dl.children.forEach((childId) => {
+        const child = downloads[childId];
+        if (child && child.status !== "COMPLETED" && child.status !== "ERROR") {
+          child.status = "COMPLETED";
+          child.progress = 100;
+          updateDownloadUI(childId);
+        }
+      });
+      updateParentProgress(id);
+      return;
+    }
+
     if (["PAUSED", "CANCELLED", "COMPLETED", "ERROR"].includes(dl.status))
       return;
 
@@ -1017,6 +1137,24 @@ document.addEventListener("DOMContentLoaded", () => {
     if (dl.parentId) updateParentProgress(dl.parentId);
   });
 
+  window.electronAPI.on("disk-space-warning", (payload) => {
+    const dl = downloads[payload.id];
+    if (!dl) return;
+    showAlert("LOW DISK SPACE", `${dl.title}: ${payload.message}`);
+  });
+
+  window.electronAPI.on("download-retrying", (payload) => {
+    const dl = downloads[payload.id];
+    if (!dl || dl.isPausing || dl.isCancelling) return;
+    dl.status = "RETRYING";
+    dl.lastSpeedValue = 0;
+    if (dl.element) {
+      dl.element.title = `${payload.reason} Attempt ${payload.attempt}/${payload.max_attempts} in ${payload.delay_secs}s`;
+    }
+    updateDownloadUI(payload.id);
+    updateGlobalStats();
+  });
+
   window.electronAPI.on("download-error", (payload) => {
     const { id, error } = payload;
     const dl = downloads[id];
@@ -1029,8 +1167,10 @@ document.addEventListener("DOMContentLoaded", () => {
     updateGlobalStats();
     if (dl.parentId) updateParentProgress(dl.parentId);
 
-    if (error.includes("Sign in to confirm you’re not a bot")) {
+    if (payload.code === "login_required" && error.includes("not a bot")) {
         showBotDetectionModal();
+    } else if (payload.code && payload.code !== "unknown") {
+        showErrorModal(dl.title, `${payload.message}\n${payload.remedy}\n\n${error}`);
     } else {
         showErrorModal(dl.title, error);
     }
@@ -1062,6 +1202,26 @@ document.addEventListener("DOMContentLoaded", () => {
     errorModalCopy.addEventListener("click", onCopy);
   }
 
+  window.electronAPI.on("download-paused", (id) => {
+    const dl = downloads[id];
+    if (!dl || !dl.element) return;
+    dl.status = "PAUSED";
+    dl.lastSpeedValue = 0;
+    updateDownloadUI(id);
+    updatePauseIcon(id, true);
+    updateGlobalStats();
+    if (dl.parentId) updateParentProgress(dl.parentId);
+  });
+
+  window.electronAPI.on("download-resumed", (id) => {
+    const dl = downloads[id];
+    if (!dl || !dl.element) return;
+    dl.isPausing = false;
+    dl.status = "QUEUED";
+    updateDownloadUI(id);
+    updatePauseIcon(id, false);
+  });
+
   window.electronAPI.on("download-cancelled", (id) => {
     const dl = downloads[id];
     if (!dl || !dl.element) return;
@@ -1137,10 +1297,11 @@ document.addEventListener("DOMContentLoaded", () => {
 
       await window.electronAPI.invoke("cancel-download", id);
 
-      if (item.path && item.videoId) {
+      if (item.path && (item.videoId || item.isGroup)) {
         await window.electronAPI.invoke("cleanup-partial-files", {
           path: item.path,
-          video_id: item.videoId,
+          video_id: item.videoId || "",
+          id,
         });
       }
 
@@ -1395,26 +1556,6 @@ document.addEventListener("DOMContentLoaded", () => {
     }
   }
 
-  function parseSpeed(speedStr) {
-    const match = speedStr.match(/([\d.]+)\s*(\w+)\/s/i);
-    if (!match) return 0;
-
-    const value = parseFloat(match[1]);
-    const unit = match[2].toLowerCase();
-
-    const multipliers = {
-      b: 1,
-      kib: 1024,
-      mib: 1024 * 1024,
-      gib: 1024 * 1024 * 1024,
-      kb: 1000,
-      mb: 1000 * 1000,
-      gb: 1000 * 1000 * 1000,
-    };
-
-    return value * (multipliers[unit] || 1);
-  }
-
   function formatSpeed(bytesPerSec) {
     if (bytesPerSec === 0) return "0 KB/S";
     const units = ["B/S", "KB/S", "MB/S", "GB/S"];
@@ -1428,6 +1569,72 @@ document.addEventListener("DOMContentLoaded", () => {
   }
 
   checkDependencies();
+  offerInterruptedResume();
+  loadEncoderOptions();
+
+  // Adds every hardware encoder that passed the backend's test encode to the
+  // acceleration picker, so a specific GPU encoder can be chosen.
+  async function loadEncoderOptions() {
+    if (!hwAccelSelect) return;
+    let encoders = [];
+    try {
+      encoders = await window.electronAPI.invoke("get-encoders", false);
+    } catch (err) {
+      console.warn("Encoder probe unavailable:", err);
+      return;
+    }
+    encoders
+      .filter((enc) => enc.hardware && enc.verified)
+      .forEach((enc) => {
+        const opt = document.createElement("option");
+        opt.value = enc.name;
+        opt.textContent = `${enc.family.toUpperCase()} ${enc.codec.toUpperCase()} (${enc.name})`;
+        hwAccelSelect.appendChild(opt);
+      });
+    hwAccelSelect.value = localStorage.getItem("pref-hw-accel") || "auto";
+    if (!hwAccelSelect.value) hwAccelSelect.value = "auto";
+  }
+
+  async function offerInterruptedResume() {
+    let interrupted = [];
+    try {
+      interrupted = await window.electronAPI.invoke("get_interrupted_downloads");
+    } catch (e) {
+      console.error("Interrupted download check failed:", e);
+      return;
+    }
+    if (!interrupted || interrupted.length === 0) return;
+
+    const ids = interrupted.map((item) => item.id);
+    const confirmed = await showConfirm(
+      "RESUME DOWNLOADS",
+      `${interrupted.length} download(s) were interrupted when the app last closed. Resume them from where they stopped?`,
+      "RESUME",
+      "DISCARD",
+    );
+    if (!confirmed) {
+      await window.electronAPI.invoke("discard_interrupted_downloads", ids);
+      return;
+    }
+
+    interrupted.forEach((item) => {
+      const download = {
+        id: item.id,
+        args: item.args,
+        title: item.args.title || item.args.url,
+        thumb: "assets/youtube.svg",
+        type: item.args.type,
+        qualityName: "Resumed",
+        path: item.args.outputDir,
+        progress: 0,
+        status: "QUEUED",
+        element: null,
+      };
+      downloads[item.id] = download;
+      addDownloadRow(download);
+    });
+    await window.electronAPI.invoke("resume_interrupted_downloads", ids);
+  }
 
   async function checkDependencies() {
     try {
@@ -1483,7 +1690,7 @@ document.addEventListener("DOMContentLoaded", () => {
         await window.electronAPI.invoke("download_dependencies");
       } catch (e) {
         console.error("Download failed:", e);
-        alert("Update failed: " + e);
+        alert("Update failed: " + (e && e.message ? e.message : e));
         depModal.classList.add("hidden");
       }
     } else if (hasYtdlpUpdate) {